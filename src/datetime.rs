@@ -0,0 +1,865 @@
+//! Date and time values, tags 0, 1 and 1001, and calendar dates, tags 100 and 1004.
+//!
+//! The submodules are meant for `#[serde(with = "...")]`:
+//!
+//! * `chrono` for `chrono::DateTime<Tz>`, `chrono::date` for `chrono::NaiveDate`,
+//!   with the `chrono` feature.
+//! * `time` for `time::OffsetDateTime`, `time::date` for `time::Date`, with the
+//!   `time` feature.
+//!
+//! They serialize as tag 0 (RFC 3339 text) or tag 1 (seconds since the epoch,
+//! an integer or a float), see [`Config::date_time`](crate::Config::date_time).
+//! Other serde formats see the RFC 3339 text.
+//!
+//! Text, integer and float timestamps are accepted when decoding, with or without
+//! their tag, as is an extended time (tag 1001, RFC 9581) map. Of its keys, the
+//! base time (1) and the milli-, micro- and nanosecond fractions (-3, -6, -9) are
+//! read, the others are ignored.
+//!
+//! Dates serialize as tag 1004 (RFC 3339 full-date text) or tag 100 (days since
+//! the epoch), see [`Config::date`](crate::Config::date), and both are accepted
+//! when decoding. Other serde formats see the full-date text.
+//!
+//! ```
+//! # #[cfg(feature = "chrono")]
+//! # {
+//! use chrono::{DateTime, Utc};
+//! use serde::{Deserialize, Serialize};
+//! use minicbor_ser::{from_slice, to_vec};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Event {
+//!     #[serde(with = "minicbor_ser::datetime::chrono")]
+//!     at: DateTime<Utc>,
+//! }
+//! # }
+//! ```
+
+#![cfg_attr(not(any(feature = "chrono", feature = "time")), allow(dead_code))]
+
+use crate::lib::*;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, Unexpected, Visitor};
+use serde::ser::{self, Serialize, Serializer};
+
+/// Extended time (RFC 9581).
+pub(crate) const EXTENDED: u64 = 1001;
+
+/// Days since 1970-01-01 (RFC 8943).
+pub(crate) const DAYS: u64 = 100;
+/// RFC 3339 full-date text (RFC 8943).
+pub(crate) const FULL_DATE: u64 = 1004;
+
+/// The newtype name timestamps serialize with.
+pub(crate) const DATETIME_NAME: &str = "@@DATETIME@@";
+/// The newtype name dates serialize with.
+pub(crate) const DATE_NAME: &str = "@@DATE@@";
+
+/// A point in time with the UTC offset it is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Timestamp {
+    /// Seconds since 1970-01-01T00:00:00Z.
+    pub secs: i64,
+    pub nanos: u32,
+    /// Seconds east of UTC.
+    pub offset: i32,
+}
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+const SECS_PER_DAY: i64 = 86_400;
+
+impl Timestamp {
+    fn utc(secs: i64, nanos: u32) -> Self {
+        Timestamp {
+            secs,
+            nanos,
+            offset: 0,
+        }
+    }
+
+    /// Seconds since the epoch as a float, which may round.
+    pub fn epoch_f64(&self) -> f64 {
+        self.secs as f64 + f64::from(self.nanos) / f64::from(NANOS_PER_SEC)
+    }
+
+    fn from_f64(v: f64) -> Option<Self> {
+        if !v.is_finite() || v.abs() >= i64::MAX as f64 {
+            return None;
+        }
+        let secs = floor(v);
+        let mut nanos = ((v - secs) * f64::from(NANOS_PER_SEC) + 0.5) as u32;
+        let mut secs = secs as i64;
+        if nanos >= NANOS_PER_SEC {
+            nanos -= NANOS_PER_SEC;
+            secs += 1;
+        }
+        Some(Timestamp::utc(secs, nanos))
+    }
+
+    /// Parse RFC 3339 text, e.g. `2013-03-21T20:04:00.5+01:00`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let b = s.as_bytes();
+        if b.len() < 20 || b[4] != b'-' || b[7] != b'-' || b[13] != b':' || b[16] != b':' {
+            return None;
+        }
+        if !matches!(b[10], b'T' | b't' | b' ') {
+            return None;
+        }
+        let year = digits(&b[0..4])? as i64;
+        let month = digits(&b[5..7])?;
+        let day = digits(&b[8..10])?;
+        let hour = digits(&b[11..13])?;
+        let minute = digits(&b[14..16])?;
+        let second = digits(&b[17..19])?;
+        if !(1..=12).contains(&month)
+            || day == 0
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 60
+        {
+            return None;
+        }
+        let mut rest = &b[19..];
+        let mut nanos = 0;
+        if rest[0] == b'.' {
+            let len = rest[1..].iter().take_while(|c| c.is_ascii_digit()).count();
+            if len == 0 {
+                return None;
+            }
+            let mut scale = NANOS_PER_SEC;
+            for &c in &rest[1..=len] {
+                scale /= 10;
+                nanos += u32::from(c - b'0') * scale;
+            }
+            rest = &rest[len + 1..];
+        }
+        let offset = match rest {
+            [b'Z'] | [b'z'] => 0,
+            [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+                let hours = digits(&[*h1, *h2])?;
+                let minutes = digits(&[*m1, *m2])?;
+                if hours > 23 || minutes > 59 {
+                    return None;
+                }
+                let offset = (hours * 3600 + minutes * 60) as i32;
+                if *sign == b'-' {
+                    -offset
+                } else {
+                    offset
+                }
+            }
+            _ => return None,
+        };
+        let local = days_from_civil(year, month, day) * SECS_PER_DAY
+            + i64::from(hour * 3600 + minute * 60 + second);
+        Some(Timestamp {
+            secs: local - i64::from(offset),
+            nanos,
+            offset,
+        })
+    }
+}
+
+/// RFC 3339 text in the offset of the timestamp, with as many fractional digits as needed.
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // offsets with seconds can't be written, fall back to UTC
+        let offset = if self.offset % 60 == 0 { self.offset } else { 0 };
+        let local = self.secs + i64::from(offset);
+        let (year, month, day) = civil_from_days(local.div_euclid(SECS_PER_DAY));
+        if !(0..=9999).contains(&year) {
+            return Err(fmt::Error);
+        }
+        let time = local.rem_euclid(SECS_PER_DAY);
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            time / 3600,
+            time / 60 % 60,
+            time % 60
+        )?;
+        if self.nanos != 0 {
+            let mut nanos = self.nanos;
+            let mut width = 9;
+            while nanos.is_multiple_of(10) {
+                nanos /= 10;
+                width -= 1;
+            }
+            write!(f, ".{:0width$}", nanos, width = width)?;
+        }
+        if offset == 0 {
+            return f.write_str("Z");
+        }
+        let sign = if offset < 0 { '-' } else { '+' };
+        let offset = offset.abs() / 60;
+        write!(f, "{}{:02}:{:02}", sign, offset / 60, offset % 60)
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let text = Text {
+            value: *self,
+            readable: serializer.is_human_readable(),
+        };
+        serializer.serialize_newtype_struct(DATETIME_NAME, &text)
+    }
+}
+
+/// A timestamp or date as its RFC 3339 text, formatted without allocating, or as
+/// its number.
+///
+/// The serializer of this crate asks for the number by answering `is_human_readable`
+/// inside the newtype differently than outside of it, other serializers get the text.
+struct Text<T> {
+    value: T,
+    readable: bool,
+}
+
+/// The number of a timestamp or date, written without going through its text.
+trait Number {
+    fn serialize_number<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+}
+
+/// Nanoseconds since the epoch.
+impl Number for Timestamp {
+    fn serialize_number<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i128(
+            i128::from(self.secs) * i128::from(NANOS_PER_SEC) + i128::from(self.nanos),
+        )
+    }
+}
+
+/// Days since the epoch.
+impl Number for Date {
+    fn serialize_number<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.days)
+    }
+}
+
+impl<T: Display + Number> Serialize for Text<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if serializer.is_human_readable() != self.readable {
+            return self.value.serialize_number(serializer);
+        }
+        let mut buf = Buf {
+            bytes: [0; 40],
+            len: 0,
+        };
+        fmt::write(&mut buf, format_args!("{}", self.value))
+            .map_err(|_| ser::Error::custom("date out of range for RFC 3339"))?;
+        // only ASCII is written
+        serializer.serialize_str(str::from_utf8(&buf.bytes[..buf.len]).unwrap_or_default())
+    }
+}
+
+struct Buf {
+    bytes: [u8; 40],
+    len: usize,
+}
+
+impl fmt::Write for Buf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.bytes.len() {
+            return Err(fmt::Error);
+        }
+        self.bytes[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(DATETIME_NAME, TimestampVisitor)
+    }
+}
+
+struct TimestampVisitor;
+
+impl<'de> Visitor<'de> for TimestampVisitor {
+    type Value = Timestamp;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an RFC 3339 date/time, epoch seconds or an extended time map")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Timestamp::parse(v).ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Timestamp::utc(v, 0))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let secs = i64::try_from(v).map_err(|_| E::invalid_value(Unexpected::Unsigned(v), &self))?;
+        Ok(Timestamp::utc(secs, 0))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Timestamp::from_f64(v).ok_or_else(|| E::invalid_value(Unexpected::Float(v), &self))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut base = None;
+        let mut nanos = 0u64;
+        while let Some(key) = map.next_key::<i64>()? {
+            let scale = match key {
+                1 => {
+                    base = Some(map.next_value::<Timestamp>()?);
+                    continue;
+                }
+                -3 => 1_000_000,
+                -6 => 1_000,
+                -9 => 1,
+                _ => {
+                    map.next_value::<de::IgnoredAny>()?;
+                    continue;
+                }
+            };
+            let fraction: u64 = map.next_value()?;
+            nanos = fraction
+                .checked_mul(scale)
+                .filter(|n| *n < u64::from(NANOS_PER_SEC))
+                .ok_or_else(|| de::Error::custom("extended time fraction exceeds one second"))?;
+        }
+        let mut ts = base.ok_or_else(|| de::Error::missing_field("1"))?;
+        if nanos != 0 {
+            ts.nanos = nanos as u32;
+        }
+        Ok(ts)
+    }
+}
+
+/// A calendar date without a time zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Date {
+    /// Days since 1970-01-01.
+    pub days: i64,
+}
+
+impl Date {
+    /// Parse RFC 3339 full-date text, e.g. `2013-03-21`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let b = s.as_bytes();
+        if b.len() != 10 || b[4] != b'-' || b[7] != b'-' {
+            return None;
+        }
+        let year = digits(&b[0..4])? as i64;
+        let month = digits(&b[5..7])?;
+        let day = digits(&b[8..10])?;
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(Date {
+            days: days_from_civil(year, month, day),
+        })
+    }
+}
+
+/// RFC 3339 full-date text.
+impl Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (year, month, day) = civil_from_days(self.days);
+        if !(0..=9999).contains(&year) {
+            return Err(fmt::Error);
+        }
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let text = Text {
+            value: *self,
+            readable: serializer.is_human_readable(),
+        };
+        serializer.serialize_newtype_struct(DATE_NAME, &text)
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(DATE_NAME, DateVisitor)
+    }
+}
+
+struct DateVisitor;
+
+impl<'de> Visitor<'de> for DateVisitor {
+    type Value = Date;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an RFC 3339 full-date or days since the epoch")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Date::parse(v).ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Date { days: v })
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let days = i64::try_from(v).map_err(|_| E::invalid_value(Unexpected::Unsigned(v), &self))?;
+        Ok(Date { days })
+    }
+}
+
+/// `floor` without `std`.
+fn floor(v: f64) -> f64 {
+    let t = v as i64 as f64;
+    if t > v {
+        t - 1.0
+    } else {
+        t
+    }
+}
+
+fn digits(b: &[u8]) -> Option<u32> {
+    b.iter().try_fold(0, |n, c| {
+        if c.is_ascii_digit() {
+            Some(n * 10 + u32::from(c - b'0'))
+        } else {
+            None
+        }
+    })
+}
+
+fn is_leap(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = i64::from(month);
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// The proleptic Gregorian date of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// `#[serde(with = "minicbor_ser::datetime::chrono")]` for `chrono::DateTime<Tz>`.
+#[cfg(feature = "chrono")]
+pub mod chrono {
+    use super::Timestamp;
+    use crate::lib::*;
+    use ::chrono::{DateTime, FixedOffset, Offset, TimeZone};
+    use serde::{de, Deserialize, Deserializer, Serializer, Serialize};
+
+    pub fn serialize<Tz, S>(dt: &DateTime<Tz>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        Tz: TimeZone,
+        S: Serializer,
+    {
+        Timestamp {
+            secs: dt.timestamp(),
+            // leap seconds are folded into the second before
+            nanos: dt.timestamp_subsec_nanos().min(999_999_999),
+            offset: dt.offset().fix().local_minus_utc(),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, Tz, D>(deserializer: D) -> Result<DateTime<Tz>, D::Error>
+    where
+        Tz: TimeZone,
+        DateTime<Tz>: From<DateTime<FixedOffset>>,
+        D: Deserializer<'de>,
+    {
+        let ts = Timestamp::deserialize(deserializer)?;
+        FixedOffset::east_opt(ts.offset)
+            .and_then(|offset| offset.timestamp_opt(ts.secs, ts.nanos).single())
+            .map(DateTime::from)
+            .ok_or_else(|| de::Error::custom("date/time out of range"))
+    }
+
+    /// `#[serde(with = "minicbor_ser::datetime::chrono::date")]` for `chrono::NaiveDate`.
+    pub mod date {
+        use super::super::Date;
+        use crate::lib::*;
+        use ::chrono::{Datelike, NaiveDate};
+        use serde::{de, Deserialize, Deserializer, Serializer, Serialize};
+
+        /// The days from 0001-01-01 to 1970-01-01.
+        const EPOCH_FROM_CE: i64 = 719_163;
+
+        pub fn serialize<S>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            Date {
+                days: i64::from(date.num_days_from_ce()) - EPOCH_FROM_CE,
+            }
+            .serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let date = Date::deserialize(deserializer)?;
+            date.days
+                .checked_add(EPOCH_FROM_CE)
+                .and_then(|days| i32::try_from(days).ok())
+                .and_then(NaiveDate::from_num_days_from_ce_opt)
+                .ok_or_else(|| de::Error::custom("date out of range"))
+        }
+    }
+}
+
+/// `#[serde(with = "minicbor_ser::datetime::time")]` for `time::OffsetDateTime`.
+#[cfg(feature = "time")]
+pub mod time {
+    use super::Timestamp;
+    use crate::lib::*;
+    use ::time::{OffsetDateTime, UtcOffset};
+    use serde::{de, Deserialize, Deserializer, Serializer, Serialize};
+
+    pub fn serialize<S>(dt: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Timestamp {
+            secs: dt.unix_timestamp(),
+            nanos: dt.nanosecond(),
+            offset: dt.offset().whole_seconds(),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let ts = Timestamp::deserialize(deserializer)?;
+        let nanos = i128::from(ts.secs) * 1_000_000_000 + i128::from(ts.nanos);
+        let offset = UtcOffset::from_whole_seconds(ts.offset).map_err(de::Error::custom)?;
+        OffsetDateTime::from_unix_timestamp_nanos(nanos)
+            .ok()
+            .and_then(|dt| dt.checked_to_offset(offset))
+            .ok_or_else(|| de::Error::custom("date/time out of range"))
+    }
+
+    /// `#[serde(with = "minicbor_ser::datetime::time::date")]` for `time::Date`.
+    pub mod date {
+        use super::super::Date;
+        use crate::lib::*;
+        use serde::{de, Deserialize, Deserializer, Serializer, Serialize};
+
+        /// The Julian day of 1970-01-01.
+        const EPOCH_JULIAN_DAY: i64 = 2_440_588;
+
+        pub fn serialize<S>(date: &::time::Date, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            Date {
+                days: i64::from(date.to_julian_day()) - EPOCH_JULIAN_DAY,
+            }
+            .serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<::time::Date, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let date = Date::deserialize(deserializer)?;
+            date.days
+                .checked_add(EPOCH_JULIAN_DAY)
+                .and_then(|days| i32::try_from(days).ok())
+                .and_then(|days| ::time::Date::from_julian_day(days).ok())
+                .ok_or_else(|| de::Error::custom("date out of range"))
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_slice, to_vec, to_vec_cfg, Config, DateRepr, DateTimeRepr};
+
+    #[test]
+    fn test_civil() {
+        for days in -800_000..800_000 {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days, days_from_civil(y, m, d));
+        }
+        assert_eq!((1970, 1, 1), civil_from_days(0));
+        assert_eq!((2000, 2, 29), civil_from_days(11_016));
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        let ts = Timestamp::parse("2013-03-21T20:04:00Z").unwrap();
+        assert_eq!(ts, Timestamp::utc(1_363_896_240, 0));
+        assert_eq!("2013-03-21T20:04:00Z", ts.to_string());
+
+        let ts = Timestamp::parse("2013-03-21T21:34:00.25+01:30").unwrap();
+        assert_eq!((1_363_896_240, 250_000_000, 5400), (ts.secs, ts.nanos, ts.offset));
+        assert_eq!("2013-03-21T21:34:00.25+01:30", ts.to_string());
+
+        let ts = Timestamp::parse("1969-12-31t23:59:59.000000001-00:00").unwrap();
+        assert_eq!(Timestamp::utc(-1, 1), ts);
+        assert_eq!("1969-12-31T23:59:59.000000001Z", ts.to_string());
+
+        for s in [
+            "2013-02-29T00:00:00Z",
+            "2013-03-21T20:04:00",
+            "2013-03-21T20:04:00.Z",
+            "2013-03-21T24:00:00Z",
+            "2013-03-21T20:04:00+1:00",
+        ] {
+            assert_eq!(None, Timestamp::parse(s), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let ts = Timestamp::utc(1_363_896_240, 0);
+        let data = to_vec(&ts).unwrap();
+        assert_eq!(data[0], 0xc0);
+        assert_eq!(&data[2..], b"2013-03-21T20:04:00Z");
+        assert_eq!(ts, from_slice(&data).unwrap());
+
+        let epoch = Config::default().date_time(DateTimeRepr::Epoch);
+        let data = to_vec_cfg(&ts, epoch).unwrap();
+        assert_eq!(data, [0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0]);
+        assert_eq!(ts, from_slice(&data).unwrap());
+
+        let ts = Timestamp::utc(1_363_896_240, 500_000_000);
+        let data = to_vec_cfg(&ts, epoch).unwrap();
+        assert_eq!(data, [0xc1, 0xfb, 0x41, 0xd4, 0x52, 0xd9, 0xec, 0x20, 0x00, 0x00]);
+        assert_eq!(ts, from_slice(&data).unwrap());
+
+        // untagged
+        assert_eq!(Timestamp::utc(-1, 0), from_slice(&[0x20]).unwrap());
+        // extended time, 1001({1: 1363896240, -3: 500})
+        let data = [
+            0xd9, 0x03, 0xe9, 0xa2, 0x01, 0x1a, 0x51, 0x4b, 0x67, 0xb0, 0x22, 0x19, 0x01, 0xf4,
+        ];
+        assert_eq!(ts, from_slice(&data).unwrap());
+        assert!(from_slice::<Timestamp>(&[0xc0, 0x61, 0x61]).is_err());
+
+        // years outside of RFC 3339 only fit into tag 1
+        for ts in [
+            Timestamp::utc(253_402_300_800, 0),
+            Timestamp::utc(-62_167_219_201, 0),
+            Timestamp::utc(253_402_300_800, 500_000_000),
+        ] {
+            assert!(to_vec(&ts).is_err());
+            let data = to_vec_cfg(&ts, epoch).unwrap();
+            assert_eq!(data[0], 0xc1);
+            assert_eq!(ts, from_slice(&data).unwrap());
+        }
+        let compact = epoch.human_readable(false);
+        let data = to_vec_cfg(&Timestamp::utc(-1, 0), compact).unwrap();
+        assert_eq!(data, [0xc1, 0x20]);
+        let data = to_vec_cfg(&Timestamp::utc(-1, 0), Config::default().human_readable(false));
+        assert_eq!(data.unwrap()[0], 0xc0);
+    }
+
+    #[test]
+    fn test_date() {
+        let date = Date::parse("2013-03-21").unwrap();
+        assert_eq!(15_785, date.days);
+        let data = to_vec(&date).unwrap();
+        assert_eq!(&data[..4], [0xd9, 0x03, 0xec, 0x6a]);
+        assert_eq!(&data[4..], b"2013-03-21");
+        assert_eq!(date, from_slice(&data).unwrap());
+
+        let days = Config::default().date(DateRepr::Days);
+        let data = to_vec_cfg(&date, days).unwrap();
+        assert_eq!(data, [0xd8, 0x64, 0x19, 0x3d, 0xa9]);
+        assert_eq!(date, from_slice(&data).unwrap());
+        // before the epoch, 100(-1)
+        assert_eq!(Date { days: -1 }, from_slice(&[0xd8, 0x64, 0x20]).unwrap());
+
+        for s in ["2013-02-29", "2013-3-21", "2013-03-21T00:00:00Z"] {
+            assert_eq!(None, Date::parse(s), "{}", s);
+        }
+        // years outside of RFC 3339 only fit into tag 100
+        for date in [Date { days: 2_932_897 }, Date { days: -719_529 }] {
+            assert!(to_vec(&date).is_err());
+            let data = to_vec_cfg(&date, days).unwrap();
+            assert_eq!(&data[..2], [0xd8, 0x64]);
+            assert_eq!(date, from_slice(&data).unwrap());
+        }
+        let data = to_vec_cfg(&Date { days: i64::MAX / 2 }, days).unwrap();
+        assert_eq!(&data[2..], [0x1b, 0x3f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        use ::chrono::{DateTime, FixedOffset, TimeZone, Utc};
+
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Event<Tz: TimeZone> {
+            #[serde(with = "super::chrono")]
+            #[serde(bound(deserialize = "DateTime<Tz>: From<DateTime<FixedOffset>>"))]
+            at: DateTime<Tz>,
+        }
+
+        let at = FixedOffset::east_opt(3600)
+            .unwrap()
+            .with_ymd_and_hms(2013, 3, 21, 21, 4, 0)
+            .unwrap();
+        let event = Event { at };
+        let data = to_vec(&event).unwrap();
+        assert_eq!(&data[7..], b"2013-03-21T21:04:00+01:00");
+        assert_eq!(event, from_slice(&data).unwrap());
+
+        let event = Event { at: at.with_timezone(&Utc) };
+        let data = to_vec_cfg(&event, Config::default().date_time(DateTimeRepr::Epoch)).unwrap();
+        assert_eq!(&data[4..], [0xc1, 0x1a, 0x51, 0x4b, 0x67, 0xb0]);
+        assert_eq!(event, from_slice(&data).unwrap());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono_date() {
+        use ::chrono::NaiveDate;
+
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Birthday(#[serde(with = "super::chrono::date")] NaiveDate);
+
+        let day = Birthday(NaiveDate::from_ymd_opt(1969, 12, 31).unwrap());
+        let data = to_vec(&day).unwrap();
+        assert_eq!(&data[4..], b"1969-12-31");
+        assert_eq!(day, from_slice(&data).unwrap());
+        let data = to_vec_cfg(&day, Config::default().date(DateRepr::Days)).unwrap();
+        assert_eq!(data, [0xd8, 0x64, 0x20]);
+        assert_eq!(day, from_slice(&data).unwrap());
+        assert!(from_slice::<Birthday>(&[0xd8, 0x64, 0x1b, 0x7f, 0, 0, 0, 0, 0, 0, 0]).is_err());
+
+        let far = Birthday(NaiveDate::from_ymd_opt(12_000, 1, 1).unwrap());
+        assert!(to_vec(&far).is_err());
+        let data = to_vec_cfg(&far, Config::default().date(DateRepr::Days)).unwrap();
+        assert_eq!(far, from_slice(&data).unwrap());
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time() {
+        use ::time::{Duration, OffsetDateTime, UtcOffset};
+
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Event {
+            #[serde(with = "super::time")]
+            at: OffsetDateTime,
+        }
+
+        let at = OffsetDateTime::from_unix_timestamp(1_363_896_240).unwrap() + Duration::milliseconds(500);
+        let event = Event { at: at.to_offset(UtcOffset::from_hms(-2, 0, 0).unwrap()) };
+        let data = to_vec(&event).unwrap();
+        assert_eq!(&data[7..], b"2013-03-21T18:04:00.5-02:00");
+        assert_eq!(event, from_slice(&data).unwrap());
+
+        let data = to_vec_cfg(&event, Config::default().date_time(DateTimeRepr::Epoch)).unwrap();
+        let epoch: Event = from_slice(&data).unwrap();
+        assert_eq!(at, epoch.at);
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time_date() {
+        use ::time::{Date, Month};
+
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Billing(#[serde(with = "super::time::date")] Date);
+
+        let day = Billing(Date::from_calendar_date(2013, Month::March, 21).unwrap());
+        let data = to_vec(&day).unwrap();
+        assert_eq!(&data[4..], b"2013-03-21");
+        assert_eq!(day, from_slice(&data).unwrap());
+        let data = to_vec_cfg(&day, Config::default().date(DateRepr::Days)).unwrap();
+        assert_eq!(data, [0xd8, 0x64, 0x19, 0x3d, 0xa9]);
+        assert_eq!(day, from_slice(&data).unwrap());
+    }
+}
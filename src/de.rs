@@ -3,46 +3,81 @@
 use crate::error::de::type_mismatch;
 
 use super::error::de::{self as error, Error};
-use super::Config;
-use minicbor::data::Type;
-use serde::de::{self, Unexpected};
+use super::{Config, TagHandler};
+use crate::{datetime, float, net, set, stringref, tag, typed_array};
+use crate::lib::*;
+use minicbor::data::{Tag, Type};
+use serde::de::{self, IntoDeserializer, Unexpected};
 
 pub struct Deserializer<'d> {
     decoder: minicbor::Decoder<'d>,
     depth: u32,
     flatten_top: bool,
-    vec_bytes: Option<u8>
+    human_readable: bool,
+    unique_sets: bool,
+    vec_bytes: Option<u8>,
+    unknown_tags: TagHandler,
+    /// Handlers registered for single tags.
+    #[cfg(feature = "alloc")]
+    tag_handlers: BTreeMap<u64, TagHandler>,
+    /// The open string reference namespaces, innermost last.
+    #[cfg(feature = "alloc")]
+    namespaces: Vec<stringref::Namespace<'d>>,
+    /// The shared values (tag 28) read so far, `None` while lent to a visitor.
+    #[cfg(feature = "std")]
+    shared: Option<crate::shared::Table>,
 }
 
 impl<'de> Deserializer<'de> {
+    /// A leading self-describe tag (55799) is skipped.
     pub fn new(data: &'de [u8]) -> Self {
-        Deserializer {
-            decoder: minicbor::Decoder::new(data),
-            depth: 0,
-            flatten_top: false,
-            vec_bytes:None,
-        }
+        Self::new_with_config(data, Config::default())
     }
+    /// A leading self-describe tag (55799) is skipped.
     pub fn new_with_config(data: &'de [u8], cfg: Config) -> Self {
+        let mut decoder = minicbor::Decoder::new(data);
+        if let Ok(Tag::Unassigned(tag::SELF_DESCRIBE)) = decoder.probe().tag() {
+            let _ = decoder.tag();
+        }
         Deserializer {
-            decoder: minicbor::Decoder::new(data),
+            decoder,
             depth: 0,
             flatten_top: cfg.top_flatten,
+            human_readable: !cfg.compact,
+            unique_sets: cfg.unique_sets,
             vec_bytes: None,
+            unknown_tags: cfg.unknown_tags,
+            #[cfg(feature = "alloc")]
+            tag_handlers: BTreeMap::new(),
+            #[cfg(feature = "alloc")]
+            namespaces: Vec::new(),
+            #[cfg(feature = "std")]
+            shared: Some(crate::shared::Table::new(
+                cfg.max_shared_values.unwrap_or(crate::shared::DEFAULT_MAX),
+            )),
         }
     }
     pub fn decoder(&mut self) -> &mut minicbor::Decoder<'de> {
         &mut self.decoder
     }
 
-    pub fn deserialize_tag<V>(&mut self, tag: V) -> Result<V::Value, Error>
+    /// Deserialize a tag and the value it encloses, visited as a two element
+    /// sequence of the tag number and the value.
+    pub fn deserialize_tag<V>(&mut self, visitor: V) -> Result<V::Value, Error>
     where
         V: de::Visitor<'de>,
     {
-        Err(type_mismatch(
-            Type::Tag,
-            "this type is not currently supported.",
-        ))
+        self.namespace()?;
+        let tag = tag::number(self.decoder.tag()?);
+        visitor.visit_seq(TagAccess::new(self, tag))
+    }
+
+    /// Decide what `deserialize_any` does with `tag`, before the tags this crate knows and
+    /// [`Config::unknown_tags`].
+    #[cfg(feature = "alloc")]
+    pub fn tag_handler(&mut self, tag: u64, handler: TagHandler) -> &mut Self {
+        self.tag_handlers.insert(tag, handler);
+        self
     }
 
     /// internal API
@@ -59,8 +94,282 @@ impl<'de> Deserializer<'de> {
         self.depth = m as u32;
         Ok(())
     }
+
+    /// Decode a half float, widened to `f32`.
+    fn f16(&mut self) -> Result<f32, Error> {
+        let pos = self.decoder.position();
+        let bits = match self.decoder.input().get(pos..pos + 3) {
+            Some([0xf9, hi, lo]) => u16::from_be_bytes([*hi, *lo]),
+            Some(_) => return Err(type_mismatch(self.decoder.datatype()?, "expected f16")),
+            None => {
+                return Err(error::make_kind_err(
+                    error::ErrorKind::EndOfInput,
+                    "End of input.",
+                ))
+            }
+        };
+        self.decoder.set_position(pos + 3);
+        Ok(float::f16_to_f32(bits))
+    }
+
+    /// Consume the break stop code that closes an indefinite length array or map.
+    fn end_indef(&mut self, len: Option<u64>) -> Result<(), Error> {
+        if len.is_none() {
+            match self.decoder.datatype()? {
+                Type::Break => self.decoder.skip()?,
+                t => return Err(type_mismatch(t, "expected break stop code(0xff)")),
+            }
+        }
+        Ok(())
+    }
+
+    /// Visit the tag ahead as `handler` says.
+    fn handle_tag<V>(&mut self, handler: TagHandler, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match handler {
+            TagHandler::Reject => Err(error::unsupported_tag(tag::number(
+                self.decoder.probe().tag()?,
+            ))),
+            TagHandler::Pass => {
+                self.decoder.tag()?;
+                de::Deserializer::deserialize_any(self, visitor)
+            }
+            TagHandler::Seq => self.deserialize_tag(visitor),
+            TagHandler::Map => {
+                let tag = tag::number(self.decoder.tag()?);
+                visitor.visit_map(TagMapAccess {
+                    des: self,
+                    tag: Some(tag),
+                })
+            }
+            TagHandler::Unit => {
+                self.decoder.tag()?;
+                de::Deserializer::deserialize_ignored_any(&mut *self, de::IgnoredAny)?;
+                visitor.visit_unit()
+            }
+        }
+    }
+
+    /// Open the string reference namespaces (tag 256) ahead.
+    fn namespace(&mut self) -> Result<(), Error> {
+        while self.decoder.datatype()? == Type::Tag
+            && self.decoder.probe().tag()? == Tag::Unassigned(stringref::NAMESPACE)
+        {
+            #[cfg(feature = "alloc")]
+            {
+                let mut probe = self.decoder.probe();
+                probe.skip()?;
+                let end = probe.position();
+                self.namespaces.push(stringref::Namespace {
+                    end,
+                    strings: Vec::new(),
+                });
+            }
+            self.decoder.tag()?;
+        }
+        Ok(())
+    }
+
+    /// Close the namespaces whose item ends before the current position.
+    fn close_namespaces(&mut self) {
+        #[cfg(feature = "alloc")]
+        {
+            let pos = self.decoder.position();
+            while self.namespaces.last().is_some_and(|ns| ns.end <= pos) {
+                self.namespaces.pop();
+            }
+        }
+    }
+
+    /// Whether a string reference (tag 25) is ahead.
+    fn at_string_ref(&mut self) -> Result<bool, Error> {
+        Ok(self.decoder.datatype()? == Type::Tag
+            && self.decoder.probe().tag()? == Tag::Unassigned(stringref::REF))
+    }
+
+    /// Decode a definite length text or byte string, or resolve a reference to one.
+    ///
+    /// Returns whether it's text. Strings are indexed in the current namespace.
+    fn string(&mut self) -> Result<(bool, &'de [u8]), Error> {
+        self.close_namespaces();
+        let string = match self.decoder.datatype()? {
+            Type::String => (true, self.decoder.str()?.as_bytes()),
+            Type::Bytes => (false, self.decoder.bytes()?),
+            Type::Tag if self.at_string_ref()? => {
+                self.decoder.tag()?;
+                let index = self.decoder.u64()?;
+                return self.resolve(index);
+            }
+            t => return Err(type_mismatch(t, "expected string")),
+        };
+        #[cfg(feature = "alloc")]
+        if let Some(ns) = self.namespaces.last_mut() {
+            if stringref::indexed(string.1.len(), ns.strings.len() as u64) {
+                ns.strings.push(string);
+            }
+        }
+        Ok(string)
+    }
+
+    /// The string with `index` in the innermost namespace.
+    fn resolve(&mut self, index: u64) -> Result<(bool, &'de [u8]), Error> {
+        #[cfg(feature = "alloc")]
+        {
+            let ns = self.namespaces.last();
+            if let Some(s) = ns.and_then(|ns| ns.strings.get(usize::try_from(index).ok()?)) {
+                return Ok(*s);
+            }
+        }
+        Err(error::make_kind_err(
+            error::ErrorKind::UnknownReference,
+            "string reference to a string that wasn't indexed",
+        ))
+    }
+
+    /// Decode a text string, see [`Deserializer::string`].
+    fn str(&mut self) -> Result<&'de str, Error> {
+        match self.string()? {
+            (true, s) => utf8(s),
+            (false, _) => Err(type_mismatch(Type::Bytes, "expected text string")),
+        }
+    }
+
+    /// Decode a byte string, see [`Deserializer::string`].
+    fn bytes(&mut self) -> Result<&'de [u8], Error> {
+        match self.string()? {
+            (false, b) => Ok(b),
+            (true, _) => Err(type_mismatch(Type::String, "expected byte string")),
+        }
+    }
+
+    /// Visit the octets of a byte string as a sequence of `u8`.
+    fn byte_seq<V>(&mut self, bytes: &'de [u8], visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let value = visitor.visit_seq(SeqAccess::new_with_bytes(self, bytes));
+        // the visitor may stop before the last octet
+        self.vec_bytes = None;
+        value
+    }
+
+    /// Decode an integer or a tag 2/3 bignum.
+    ///
+    /// Returns the sign and the magnitude `m`; a negative value is `-1 - m`.
+    fn big_int(&mut self) -> Result<(bool, u128), Error> {
+        if self.decoder.datatype()? != Type::Tag {
+            let n = i128::from(self.decoder.int()?);
+            return Ok(if n < 0 {
+                (true, (-1 - n) as u128)
+            } else {
+                (false, n as u128)
+            });
+        }
+        let neg = match self.decoder.tag()? {
+            Tag::PosBignum => false,
+            Tag::NegBignum => true,
+            _ => return Err(type_mismatch(Type::Tag, "expected bignum tag(2 or 3)")),
+        };
+        let bytes = self.bytes()?;
+        let zeros = bytes.iter().take_while(|b| **b == 0).count();
+        let bytes = &bytes[zeros..];
+        if bytes.len() > 16 {
+            return Err(error::make_kind_err(
+                error::ErrorKind::IntegerOverflow,
+                "bignum does not fit into 128 bits",
+            ));
+        }
+        let mut buf = [0u8; 16];
+        buf[16 - bytes.len()..].copy_from_slice(bytes);
+        Ok((neg, u128::from_be_bytes(buf)))
+    }
+
+    /// Fail if the item that starts at `start` and ends at the current position
+    /// is encoded like one of the items `seen` before.
+    fn check_unique(&self, seen: &mut Seen<'de>, start: usize) -> Result<(), Error> {
+        let input = self.decoder.input();
+        let item = &input[start..self.decoder.position()];
+        #[cfg(feature = "alloc")]
+        let duplicate = !seen.items.insert(item);
+        // without a set to keep them in, the earlier items are skipped through again
+        #[cfg(not(feature = "alloc"))]
+        let duplicate = {
+            let mut probe = minicbor::Decoder::new(input);
+            probe.set_position(seen.first);
+            let mut found = false;
+            while probe.position() < start && !found {
+                let begin = probe.position();
+                probe.skip()?;
+                found = &input[begin..probe.position()] == item;
+            }
+            found
+        };
+        if duplicate {
+            return Err(error::make_kind_err(
+                error::ErrorKind::DuplicateElement,
+                "set contains an element twice",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the bignum ahead doesn't fit into an `i128` or `u128`.
+    fn wide_bignum(&mut self) -> Result<bool, Error> {
+        self.close_namespaces();
+        let mut probe = self.decoder.probe();
+        let neg = probe.tag()? == Tag::NegBignum;
+        let bytes = if probe.datatype()? == Type::Tag {
+            probe.tag()?;
+            let index = probe.u64()?;
+            self.resolve(index)?.1
+        } else {
+            probe.bytes()?
+        };
+        let bytes = &bytes[bytes.iter().take_while(|b| **b == 0).count()..];
+        Ok(bytes.len() > 16 || (neg && bytes.len() == 16 && bytes[0] >= 0x80))
+    }
+
+    /// Hand a bignum to `visitor` using the narrowest integer type that holds it.
+    fn visit_big_int<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.big_int()? {
+            (false, m) => match u64::try_from(m) {
+                Ok(n) => visitor.visit_u64(n),
+                Err(_) => visitor.visit_u128(m),
+            },
+            (true, m) => {
+                let n = to_negative(m)?;
+                match i64::try_from(n) {
+                    Ok(n) => visitor.visit_i64(n),
+                    Err(_) => visitor.visit_i128(n),
+                }
+            }
+        }
+    }
+}
+
+/// The text of a text string.
+#[inline]
+fn utf8(s: &[u8]) -> Result<&str, Error> {
+    str::from_utf8(s)
+        .map_err(|_| error::make_kind_err(error::ErrorKind::InvalidChar, "invalid UTF-8 in string"))
 }
 
+#[inline]
+fn to_negative(m: u128) -> Result<i128, Error> {
+    i128::try_from(m).map(|m| -1 - m).map_err(|_| {
+        error::make_kind_err(
+            error::ErrorKind::IntegerOverflow,
+            "negative integer does not fit into i128",
+        )
+    })
+}
+
+#[allow(clippy::needless_lifetimes)]
 impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     type Error = error::Error;
 
@@ -68,6 +377,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
         match self.decoder.datatype()? {
             Type::Bool => self.deserialize_bool(visitor),
             Type::Null => self.deserialize_option(visitor),
@@ -80,7 +390,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
             Type::I16 => self.deserialize_i16(visitor),
             Type::I32 => self.deserialize_i32(visitor),
             Type::I64 => self.deserialize_i64(visitor),
-            Type::F16 => Err(type_mismatch(Type::F16, "rust doesn't support this type")),
+            Type::F16 => self.deserialize_f32(visitor),
             Type::F32 => self.deserialize_f32(visitor),
             Type::F64 => self.deserialize_f64(visitor),
             Type::Simple => Err(type_mismatch(
@@ -95,7 +405,43 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
             Type::ArrayIndef => self.deserialize_seq(visitor),
             Type::Map => self.deserialize_map(visitor),
             Type::MapIndef => self.deserialize_map(visitor),
-            Type::Tag => self.deserialize_tag(visitor),
+            Type::Tag => match self.decoder.probe().tag()? {
+                #[cfg(feature = "alloc")]
+                tag if self.tag_handlers.contains_key(&tag::number(tag)) => {
+                    let handler = self.tag_handlers[&tag::number(tag)];
+                    self.handle_tag(handler, visitor)
+                }
+                // wider bignums are visited as `[tag, bytes]`
+                Tag::PosBignum | Tag::NegBignum if self.wide_bignum()? => {
+                    self.deserialize_tag(visitor)
+                }
+                Tag::PosBignum | Tag::NegBignum => self.visit_big_int(visitor),
+                // the content of these tags stands on its own
+                Tag::DateTime
+                | Tag::Timestamp
+                | Tag::Uri
+                | Tag::Base64Url
+                | Tag::Base64
+                | Tag::Regex
+                | Tag::Mime
+                | Tag::Unassigned(tag::UUID)
+                | Tag::Unassigned(tag::SELF_DESCRIBE)
+                | Tag::Unassigned(datetime::EXTENDED)
+                | Tag::Unassigned(datetime::DAYS)
+                | Tag::Unassigned(datetime::FULL_DATE) => {
+                    self.decoder.tag()?;
+                    self.deserialize_any(visitor)
+                }
+                Tag::Unassigned(set::SET) => self.deserialize_seq(visitor),
+                Tag::Unassigned(stringref::REF) => match self.string()? {
+                    (true, s) => visitor.visit_borrowed_str(utf8(s)?),
+                    (false, b) => visitor.visit_borrowed_bytes(b),
+                },
+                Tag::Unassigned(n) if typed_array::layout(n).is_some() => {
+                    self.deserialize_seq(visitor)
+                }
+                _ => self.handle_tag(self.unknown_tags, visitor),
+            },
             Type::Break => Err(type_mismatch(
                 Type::Break,
                 "break stop code outside indefinite length item",
@@ -104,7 +450,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
                 Type::Unknown(u),
                 "rust doesn't support this type",
             )),
-            Type::Int => todo!(),
+            Type::Int => self.visit_big_int(visitor),
         }
     }
 
@@ -112,6 +458,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
         visitor.visit_bool(self.decoder.bool()?)
     }
 
@@ -119,6 +466,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
         visitor.visit_i8(self.decoder.i8()?)
     }
 
@@ -126,6 +474,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
         visitor.visit_i16(self.decoder.i16()?)
     }
 
@@ -133,6 +482,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
         visitor.visit_i32(self.decoder.i32()?)
     }
 
@@ -140,9 +490,28 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
         visitor.visit_i64(self.decoder.i64()?)
     }
 
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.namespace()?;
+        match self.big_int()? {
+            (false, m) => match i128::try_from(m) {
+                Ok(n) => visitor.visit_i128(n),
+                Err(_) => Err(error::make_kind_err(
+                    error::ErrorKind::IntegerOverflow,
+                    "integer does not fit into i128",
+                )),
+            },
+            (true, m) => visitor.visit_i128(to_negative(m)?),
+        }
+    }
+
+    #[allow(clippy::needless_return)]
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
@@ -150,6 +519,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         if let Some(bs) = self.vec_bytes{
             return visitor.visit_u8(bs)
         }else {
+            self.namespace()?;
             visitor.visit_u8(self.decoder.u8()?)
         }
     }
@@ -158,6 +528,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
         visitor.visit_u16(self.decoder.u16()?)
     }
 
@@ -165,6 +536,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
         visitor.visit_u32(self.decoder.u32()?)
     }
 
@@ -172,13 +544,32 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
         visitor.visit_u64(self.decoder.u64()?)
     }
 
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.namespace()?;
+        match self.big_int()? {
+            (false, m) => visitor.visit_u128(m),
+            (true, _) => Err(error::make_kind_err(
+                error::ErrorKind::IntegerOverflow,
+                "negative integer can not be decoded as u128",
+            )),
+        }
+    }
+
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
+        if self.decoder.datatype()? == Type::F16 {
+            return visitor.visit_f32(self.f16()?);
+        }
         visitor.visit_f32(self.decoder.f32()?)
     }
 
@@ -186,6 +577,10 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
+        if self.decoder.datatype()? == Type::F16 {
+            return visitor.visit_f64(f64::from(self.f16()?));
+        }
         visitor.visit_f64(self.decoder.f64()?)
     }
 
@@ -193,6 +588,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
         visitor.visit_char(self.decoder.char()?)
     }
 
@@ -200,7 +596,16 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_borrowed_str(self.decoder.str()?)
+        self.namespace()?;
+        #[cfg(feature = "alloc")]
+        if self.decoder.datatype()? == Type::StringIndef {
+            let mut s = String::new();
+            for chunk in self.decoder.str_iter()? {
+                s.push_str(chunk?);
+            }
+            return visitor.visit_string(s);
+        }
+        visitor.visit_borrowed_str(self.str()?)
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -214,7 +619,16 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_borrowed_bytes(self.decoder.bytes()?)
+        self.namespace()?;
+        #[cfg(feature = "alloc")]
+        if self.decoder.datatype()? == Type::BytesIndef {
+            let mut b = Vec::new();
+            for chunk in self.decoder.bytes_iter()? {
+                b.extend_from_slice(chunk?);
+            }
+            return visitor.visit_byte_buf(b);
+        }
+        visitor.visit_borrowed_bytes(self.bytes()?)
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -228,6 +642,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
         match self.decoder.datatype()? {
             Type::Null | Type::Undefined => {
                 self.decoder.skip()?;
@@ -241,8 +656,12 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
         match self.decoder.datatype()? {
-            Type::Null | Type::Undefined => visitor.visit_unit(),
+            Type::Null | Type::Undefined => {
+                self.decoder.skip()?;
+                visitor.visit_unit()
+            }
             _ => Err(type_mismatch(Type::Null, "expected unit(null)")),
         }
     }
@@ -266,6 +685,54 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
+        // `float::half` is a newtype of the bits, but also accepts floats.
+        #[cfg(feature = "half")]
+        if name == crate::ser::HALF_NAME {
+            if let Type::F16 | Type::F32 | Type::F64 = self.decoder.datatype()? {
+                return self.deserialize_f32(visitor);
+            }
+        }
+        match name {
+            tag::TAGGED_NAME => return self.deserialize_tag(visitor),
+            #[cfg(feature = "std")]
+            crate::shared::SHARED_NAME if self.decoder.datatype()? == Type::Tag => {
+                if let Tag::Unassigned(crate::shared::SHAREABLE | crate::shared::SHARED_REF) =
+                    self.decoder.probe().tag()?
+                {
+                    // the table is already lent for an enclosing shared value
+                    let table = match self.shared.take() {
+                        Some(table) => table,
+                        None => return self.deserialize_tag(visitor),
+                    };
+                    let (result, table) =
+                        crate::shared::lend(table, || self.deserialize_tag(visitor));
+                    self.shared = Some(table);
+                    return result;
+                }
+            }
+            #[cfg(feature = "url")]
+            tag::MAYBE_TAGGED_NAME => {
+                if self.decoder.datatype()? == Type::Tag && !self.at_string_ref()? {
+                    return self.deserialize_tag(visitor);
+                }
+                return self.deserialize_any(visitor);
+            }
+            datetime::DATETIME_NAME | datetime::DATE_NAME => return self.deserialize_any(visitor),
+            // tags and simple values are visited as enums, see `Value`
+            #[cfg(feature = "alloc")]
+            crate::value::VALUE_NAME => {
+                let special = match self.decoder.datatype()? {
+                    Type::Tag if !self.at_string_ref()? => {
+                        Special::Tag(tag::number(self.decoder.tag()?))
+                    }
+                    Type::Simple => Special::Simple(self.decoder.simple()?),
+                    _ => return self.deserialize_any(visitor),
+                };
+                return visitor.visit_enum(SpecialAccess { des: self, special });
+            }
+            _ => {}
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -273,18 +740,55 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
+        let mut unique = false;
+        if self.decoder.datatype()? == Type::Tag
+            && self.decoder.probe().tag()? == Tag::Unassigned(set::SET)
+        {
+            self.decoder.tag()?;
+            unique = self.unique_sets;
+        }
+        if self.decoder.datatype()? == Type::Tag {
+            if let Some(layout) = typed_array::layout(tag::number(self.decoder.probe().tag()?)) {
+                self.decoder.tag()?;
+                let bytes = self.bytes()?;
+                if !bytes.len().is_multiple_of(layout.kind.size()) {
+                    return Err(de::Error::invalid_length(
+                        bytes.len(),
+                        &"a whole number of elements",
+                    ));
+                }
+                return visitor.visit_seq(TypedSeqAccess { bytes, layout });
+            }
+        }
         match self.decoder.datatype()? {
             Type::Array | Type::ArrayIndef => {
                 let len = self.decoder.array()?;
                 self.depth_add(1)?;
-                let v = visitor.visit_seq(SeqAccess::new(self, len));
+                let mut seq = SeqAccess::new(self, len);
+                if unique {
+                    seq.seen = Some(Seen {
+                        #[cfg(feature = "alloc")]
+                        items: BTreeSet::new(),
+                        #[cfg(not(feature = "alloc"))]
+                        first: seq.des.decoder.position(),
+                        #[cfg(not(feature = "alloc"))]
+                        _input: PhantomData,
+                    });
+                }
+                let v = visitor.visit_seq(seq)?;
                 self.depth_add(-1)?;
-                v
+                self.end_indef(len)?;
+                Ok(v)
             },
             Type::Bytes => {
-                let bytes = self.decoder.bytes()?;
-                visitor.visit_seq(SeqAccess::new_with_bytes(self, bytes))
+                let bytes = self.bytes()?;
+                self.byte_seq(bytes, visitor)
             },
+            Type::Tag if self.at_string_ref()? => {
+                let bytes = self.bytes()?;
+                self.byte_seq(bytes, visitor)
+            }
             e => {
                 if self.flatten_top && self.depth == 0 {
                     return visitor.visit_seq(SeqAccess::new(self, None));
@@ -298,6 +802,15 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
+        // the octets of `Ipv4Addr` and `Ipv6Addr` may be tagged
+        if self.decoder.datatype()? == Type::Tag {
+            if let (4, Tag::Unassigned(net::IPV4)) | (16, Tag::Unassigned(net::IPV6)) =
+                (len, self.decoder.probe().tag()?)
+            {
+                self.decoder.tag()?;
+            }
+        }
         self.deserialize_seq(visitor)
     }
 
@@ -317,13 +830,15 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
         match self.decoder.datatype()? {
             Type::Map | Type::MapIndef => {
                 let len = self.decoder.map()?;
                 self.depth_add(1)?;
-                let v = visitor.visit_map(MapAccess::new(self, len));
+                let v = visitor.visit_map(MapAccess::new(self, len))?;
                 self.depth_add(-1)?;
-                v
+                self.end_indef(len)?;
+                Ok(v)
             }
             e=> Err(type_mismatch(e, "expected map")),
         }
@@ -338,20 +853,23 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
         match self.decoder.datatype()? {
             Type::Map | Type::MapIndef => {
                 let len = self.decoder.map()?;
                 self.depth_add(1)?;
-                let v = visitor.visit_map(MapAccess::new(self, len));
+                let v = visitor.visit_map(MapAccess::new(self, len))?;
                 self.depth_add(-1)?;
-                v
+                self.end_indef(len)?;
+                Ok(v)
             }
             Type::Array | Type::ArrayIndef => {
                 let len = self.decoder.array()?;
                 self.depth_add(1)?;
-                let v = visitor.visit_seq(SeqAccess::new(self, len));
+                let v = visitor.visit_seq(SeqAccess::new(self, len))?;
                 self.depth_add(-1)?;
-                v
+                self.end_indef(len)?;
+                Ok(v)
             }
             e => {
                 if self.flatten_top && self.depth == 0 {
@@ -362,6 +880,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
         }
     }
 
+    #[allow(clippy::partialeq_to_none)]
     fn deserialize_enum<V>(
         self,
         name: &'static str,
@@ -371,8 +890,39 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        self.namespace()?;
+        // the compact form of std's `IpAddr`, tag 52 or 54 over the octets
+        if name == net::IP_ADDR_NAME
+            && variants == net::IP_ADDR_VARIANTS
+            && self.decoder.datatype()? == Type::Tag
+        {
+            let variant = match self.decoder.probe().tag()? {
+                Tag::Unassigned(net::IPV4) => Some(0),
+                Tag::Unassigned(net::IPV6) => Some(1),
+                _ => None,
+            };
+            if let Some(variant) = variant {
+                self.decoder.tag()?;
+                return visitor.visit_enum(EnumTagAccess::new(self, variant));
+            }
+        }
+        // the variants of a tag-dispatched enum are named after their tag number
+        if name == tag::TAGGED_NAME {
+            let tag = tag::number(self.decoder.tag()?);
+            let variant = variants
+                .iter()
+                .position(|v| v.parse() == Ok(tag))
+                .ok_or_else(|| error::unsupported_tag(tag))?;
+            return visitor.visit_enum(EnumTagAccess::new(self, variant as u32));
+        }
         match self.decoder.datatype()? {
-            Type::String | Type::StringIndef => visitor.visit_enum(EnumUnitAccess::new(self)),
+            Type::String
+            | Type::StringIndef
+            | Type::U8
+            | Type::U16
+            | Type::U32
+            | Type::U64 => visitor.visit_enum(EnumUnitAccess::new(self)),
+            Type::Tag if self.at_string_ref()? => visitor.visit_enum(EnumUnitAccess::new(self)),
             Type::Map | Type::MapIndef => {
                 let len = self.decoder.map()?;
                 if len == Some(1) || len == None {
@@ -385,12 +935,24 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
                             "expected map with 1 element, but break code(0xff) was not found",
                         ));
                     }
+                    self.end_indef(len)?;
                     Ok(value)
                 } else {
                     Err(type_mismatch(Type::Map, "expected map with 1 element"))
                 }
             }
-            t => Err(type_mismatch(t, "expected map or string")),
+            Type::Array | Type::ArrayIndef => {
+                let len = self.decoder.array()?;
+                if len == Some(0) {
+                    return Err(type_mismatch(Type::Array, "expected array with a variant"));
+                }
+                self.depth_add(1)?;
+                let value = visitor.visit_enum(EnumArrayAccess::new(self, len))?;
+                self.depth_add(-1)?;
+                self.end_indef(len)?;
+                Ok(value)
+            }
+            t => Err(type_mismatch(t, "expected map, array, string or integer")),
         }
     }
 
@@ -398,7 +960,12 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_str(visitor)
+        self.namespace()?;
+        // Variants and fields may also be identified by their index.
+        match self.decoder.datatype()? {
+            Type::U8 | Type::U16 | Type::U32 | Type::U64 => self.deserialize_u64(visitor),
+            _ => self.deserialize_str(visitor),
+        }
     }
 
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -407,21 +974,50 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     {
         self.deserialize_any(visitor)
     }
+
+    fn is_human_readable(&self) -> bool {
+        self.human_readable
+    }
 }
 
 struct SeqAccess<'a, 'de: 'a> {
     des: &'a mut Deserializer<'de>,
     len: Option<u64>,
     index: u64,
-    bytes: Option<&'a [u8]>
+    bytes: Option<&'a [u8]>,
+    /// The elements read so far, if duplicates are rejected.
+    seen: Option<Seen<'de>>,
+}
+
+/// The encoded elements of a set read so far.
+struct Seen<'de> {
+    #[cfg(feature = "alloc")]
+    items: BTreeSet<&'de [u8]>,
+    /// Where the elements start.
+    #[cfg(not(feature = "alloc"))]
+    first: usize,
+    #[cfg(not(feature = "alloc"))]
+    _input: PhantomData<&'de [u8]>,
 }
 
 impl<'a, 'de> SeqAccess<'a, 'de> {
     fn new(des: &'a mut Deserializer<'de>, len: Option<u64>) -> Self {
-        SeqAccess { des, len,  index: 0, bytes: None }
+        SeqAccess { des, len,  index: 0, bytes: None, seen: None }
     }
     fn new_with_bytes(des: &'a mut Deserializer<'de>, bytes: &'a [u8]) -> Self{
-        SeqAccess { des, len: Some(bytes.len() as u64), index: 0, bytes: Some(bytes) }
+        SeqAccess { des, len: Some(bytes.len() as u64), index: 0, bytes: Some(bytes), seen: None }
+    }
+
+    fn element<T>(&mut self, seed: T) -> Result<T::Value, Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        let start = self.des.decoder.position();
+        let value = seed.deserialize(&mut *self.des)?;
+        if let Some(seen) = self.seen.as_mut() {
+            self.des.check_unique(seen, start)?;
+        }
+        Ok(value)
     }
 }
 
@@ -446,7 +1042,7 @@ impl<'de, 'a> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
                 let decoder = self.des.decoder();
                 match decoder.datatype()? {
                     Type::Break => Ok(None),
-                    _ => Ok(Some(seed.deserialize(&mut *self.des)?)),
+                    _ => Ok(Some(self.element(seed)?)),
                 }
             }
             Some(len) => {
@@ -457,12 +1053,125 @@ impl<'de, 'a> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
                     self.des.vec_bytes = None;
                     return Ok(None);
                 }
-                Ok(Some(seed.deserialize(&mut *self.des)?))
+                Ok(Some(self.element(seed)?))
             }
         }
     }
 }
 
+/// The elements of a typed array (RFC 8746), one number per chunk of bytes.
+/// The elements of a typed array.
+pub(crate) struct TypedSeqAccess<'a> {
+    pub(crate) bytes: &'a [u8],
+    pub(crate) layout: typed_array::Layout,
+}
+
+impl<'de> de::SeqAccess<'de> for TypedSeqAccess<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        use typed_array::{Endian, Kind};
+
+        if self.bytes.is_empty() {
+            return Ok(None);
+        }
+        let (chunk, rest) = self.bytes.split_at(self.layout.kind.size());
+        self.bytes = rest;
+        let push = |bits: u64, b: &u8| bits << 8 | *b as u64;
+        let bits = match self.layout.endian {
+            Endian::Big => chunk.iter().fold(0, push),
+            Endian::Little => chunk.iter().rev().fold(0, push),
+        };
+        let shift = 64 - 8 * chunk.len() as u32;
+        let signed = ((bits << shift) as i64) >> shift;
+        let value = match self.layout.kind {
+            Kind::U8 => seed.deserialize((bits as u8).into_deserializer()),
+            Kind::U16 => seed.deserialize((bits as u16).into_deserializer()),
+            Kind::U32 => seed.deserialize((bits as u32).into_deserializer()),
+            Kind::U64 => seed.deserialize(bits.into_deserializer()),
+            Kind::I8 => seed.deserialize((signed as i8).into_deserializer()),
+            Kind::I16 => seed.deserialize((signed as i16).into_deserializer()),
+            Kind::I32 => seed.deserialize((signed as i32).into_deserializer()),
+            Kind::I64 => seed.deserialize(signed.into_deserializer()),
+            Kind::F16 => seed.deserialize(float::f16_to_f32(bits as u16).into_deserializer()),
+            Kind::F32 => seed.deserialize(f32::from_bits(bits as u32).into_deserializer()),
+            Kind::F64 => seed.deserialize(f64::from_bits(bits).into_deserializer()),
+        };
+        value.map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.bytes.len() / self.layout.kind.size())
+    }
+}
+
+/// `tag(value)` as `[tag, value]`
+struct TagAccess<'a, 'de: 'a> {
+    des: &'a mut Deserializer<'de>,
+    tag: u64,
+    index: u8,
+}
+
+impl<'a, 'de> TagAccess<'a, 'de> {
+    fn new(des: &'a mut Deserializer<'de>, tag: u64) -> Self {
+        TagAccess { des, tag, index: 0 }
+    }
+}
+
+impl<'de, 'a> de::SeqAccess<'de> for TagAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        self.index += 1;
+        match self.index {
+            1 => seed.deserialize(self.tag.into_deserializer()).map(Some),
+            2 => seed.deserialize(&mut *self.des).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(2usize.saturating_sub(self.index as usize))
+    }
+}
+
+/// `tag(value)` as `{tag: value}`
+struct TagMapAccess<'a, 'de: 'a> {
+    des: &'a mut Deserializer<'de>,
+    tag: Option<u64>,
+}
+
+impl<'de, 'a> de::MapAccess<'de> for TagMapAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.tag {
+            Some(tag) => seed.deserialize(tag.into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        self.tag = None;
+        seed.deserialize(&mut *self.des)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.tag.is_some() as usize)
+    }
+}
 
 struct MapAccess<'a, 'de: 'a> {
     des: &'a mut Deserializer<'de>,
@@ -637,6 +1346,196 @@ impl<'de, 'a> de::VariantAccess<'de> for EnumUnitAccess<'a, 'de> {
     }
 }
 
+/// `[variant, field1, field2, ...]`
+struct EnumArrayAccess<'a, 'de: 'a> {
+    des: &'a mut Deserializer<'de>,
+    len: Option<u64>,
+}
+impl<'a, 'de> EnumArrayAccess<'a, 'de> {
+    fn new(des: &'a mut Deserializer<'de>, len: Option<u64>) -> Self {
+        EnumArrayAccess { des, len }
+    }
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for EnumArrayAccess<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        Ok((seed.deserialize(&mut *self.des)?, self))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for EnumArrayAccess<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.len {
+            Some(1) | None => Ok(()),
+            _ => Err(type_mismatch(Type::Array, "expected array with 1 element")),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.len {
+            Some(2) | None => seed.deserialize(self.des),
+            _ => Err(type_mismatch(Type::Array, "expected array with 2 elements")),
+        }
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let len = self.len.map(|n| n - 1);
+        visitor.visit_seq(SeqAccess::new(self.des, len))
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let len = self.len.map(|n| n - 1);
+        visitor.visit_seq(SeqAccess::new(self.des, len))
+    }
+}
+
+/// A newtype variant identified by a tag.
+struct EnumTagAccess<'a, 'de: 'a> {
+    des: &'a mut Deserializer<'de>,
+    variant: u32,
+}
+impl<'a, 'de> EnumTagAccess<'a, 'de> {
+    fn new(des: &'a mut Deserializer<'de>, variant: u32) -> Self {
+        EnumTagAccess { des, variant }
+    }
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for EnumTagAccess<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant: de::value::U32Deserializer<Error> = self.variant.into_deserializer();
+        Ok((seed.deserialize(variant)?, self))
+    }
+}
+
+impl<'de, 'a> de::VariantAccess<'de> for EnumTagAccess<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Err(type_mismatch(Type::Tag, "expected unit variant"))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.des)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(type_mismatch(Type::Tag, "expected tuple variant"))
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(type_mismatch(Type::Tag, "expected struct variant"))
+    }
+}
+
+/// A tag or simple value, as the variant of a `Value`.
+#[cfg(feature = "alloc")]
+enum Special {
+    Tag(u64),
+    Simple(u8),
+}
+
+#[cfg(feature = "alloc")]
+struct SpecialAccess<'a, 'de: 'a> {
+    des: &'a mut Deserializer<'de>,
+    special: Special,
+}
+
+#[cfg(feature = "alloc")]
+impl<'de, 'a> de::EnumAccess<'de> for SpecialAccess<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = match self.special {
+            Special::Tag(_) => crate::value::TAG_VARIANT,
+            Special::Simple(_) => crate::value::SIMPLE_VARIANT,
+        };
+        let variant: de::value::U32Deserializer<Error> = variant.into_deserializer();
+        Ok((seed.deserialize(variant)?, self))
+    }
+}
+
+/// A tag is a `(tag, value)` tuple variant, a simple value a newtype variant.
+#[cfg(feature = "alloc")]
+impl<'de, 'a> de::VariantAccess<'de> for SpecialAccess<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Err(type_mismatch(Type::Tag, "expected unit variant"))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.special {
+            Special::Simple(v) => seed.deserialize(v.into_deserializer()),
+            Special::Tag(_) => Err(type_mismatch(Type::Tag, "expected tuple variant")),
+        }
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.special {
+            Special::Tag(tag) => visitor.visit_seq(TagAccess::new(self.des, tag)),
+            Special::Simple(_) => Err(type_mismatch(Type::Simple, "expected newtype variant")),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        Err(type_mismatch(Type::Tag, "expected struct variant"))
+    }
+}
+
 #[inline]
 pub fn from_slice<'a, T>(data: &'a [u8]) -> Result<T, Error>
 where
@@ -651,7 +1550,7 @@ pub fn from_slice_flat<'a, T>(data: &'a [u8]) -> Result<T, Error>
 where
     T: de::Deserialize<'a>,
 {
-    let mut deserializer = Deserializer::new_with_config(data, Config { top_flatten: true });
+    let mut deserializer = Deserializer::new_with_config(data, Config::default().top_flatten(true));
     let value = T::deserialize(&mut deserializer)?;
     Ok(value)
 }
@@ -665,8 +1564,11 @@ pub mod de_test {
 
     #[cfg(not(feature = "std"))]
     use crate::lib::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
 
     use crate::lib::BTreeMap;
+    use crate::EnumRepr;
 
     #[test]
     fn test_seq() {
@@ -750,6 +1652,197 @@ pub mod de_test {
         assert_eq!(exp, value);
     }
 
+    #[test]
+    fn test_variant_index() {
+        let index = Config::default().variant_index(true);
+        for value in [
+            TestEnum::A,
+            TestEnum::B(0xff),
+            TestEnum::D((1, 2)),
+            TestEnum::E { a: 1 },
+        ] {
+            let data = crate::to_vec_cfg(&value, index).unwrap();
+            assert_eq!(value, from_slice::<TestEnum>(&data).unwrap(), "data: {:x?}", data);
+        }
+        let value: TestEnum = from_slice(&[0xa1, 0x01, 0x05]).unwrap();
+        assert_eq!(TestEnum::B(5), value);
+        assert!(from_slice::<TestEnum>(&[0x09]).is_err());
+    }
+
+    #[test]
+    fn test_enum_array() {
+        let value: TestEnum = from_slice(&[0x82, 0x61, 0x42, 0x05]).unwrap();
+        assert_eq!(TestEnum::B(5), value);
+        let value: TestEnum = from_slice(&[0x82, 0x03, 0x82, 0x01, 0x02]).unwrap();
+        assert_eq!(TestEnum::D((1, 2)), value);
+        let value: TestEnum = from_slice(&[0x9f, 0x04, 0x01, 0xff]).unwrap();
+        assert_eq!(TestEnum::E { a: 1 }, value);
+        let value: TestEnum = from_slice(&[0x81, 0x00]).unwrap();
+        assert_eq!(TestEnum::A, value);
+        assert!(from_slice::<TestEnum>(&[0x83, 0x01, 0x01, 0x02]).is_err());
+
+        for cfg in [
+            Config::default().enum_repr(EnumRepr::Array),
+            Config::default().enum_repr(EnumRepr::Array).variant_index(true),
+        ] {
+            for value in [
+                TestEnum::A,
+                TestEnum::B(0xff),
+                TestEnum::C(TestStruct { a: 1, b: 2, c: 3, d: 4 }),
+                TestEnum::D((1, 2)),
+                TestEnum::E { a: 1 },
+            ] {
+                let data = crate::to_vec_cfg(&value, cfg).unwrap();
+                assert_eq!(value, from_slice::<TestEnum>(&data).unwrap(), "data: {:x?}", data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_f16() {
+        let value: f32 = from_slice(&[0xf9, 0x3e, 0x00]).unwrap();
+        assert_eq!(1.5, value);
+        let value: f64 = from_slice(&[0xf9, 0xfc, 0x00]).unwrap();
+        assert_eq!(f64::NEG_INFINITY, value);
+        let value: f64 = from_slice(&[0xf9, 0x00, 0x01]).unwrap();
+        assert_eq!(5.960_464_477_539_063e-8, value);
+        let value: Vec<f64> = from_slice(&[0x82, 0xf9, 0x3c, 0x00, 0xfa, 0x47, 0xc3, 0x50, 0x00]).unwrap();
+        assert_eq!(vec![1.0, 100000.0], value);
+        assert!(from_slice::<f32>(&[0xf9, 0x3e]).is_err());
+
+        let preferred = Config::default().preferred_float(true);
+        for v in [0.0, -0.0, 1.5, 65504.0, 100000.0, 0.1, f64::MAX, f64::INFINITY] {
+            let data = crate::to_vec_cfg(&v, preferred).unwrap();
+            assert_eq!(v.to_bits(), from_slice::<f64>(&data).unwrap().to_bits());
+        }
+    }
+
+    #[cfg(feature = "half")]
+    #[test]
+    fn test_half() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Half(#[serde(with = "crate::float::half")] half::f16);
+        let value: Half = from_slice(&[0xf9, 0x3e, 0x00]).unwrap();
+        assert_eq!(Half(half::f16::from_f32(1.5)), value);
+        let value: Half = from_slice(&[0x19, 0x3e, 0x00]).unwrap();
+        assert_eq!(Half(half::f16::from_f32(1.5)), value);
+
+        #[allow(non_camel_case_types)]
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct f16(u16);
+        assert!(from_slice::<f16>(&[0xf9, 0x3e, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_indefinite_str() {
+        let data = [0x7f, 0x62, 0x61, 0x62, 0x61, 0x63, 0xff];
+        let value: String = from_slice(&data).unwrap();
+        assert_eq!("abc", value);
+        let data = [0x5f, 0x41, 0x01, 0x42, 0x02, 0x03, 0xff];
+        let value: serde_bytes_like::ByteBuf = from_slice(&data).unwrap();
+        assert_eq!(vec![1, 2, 3], value.0);
+        // borrowing is not possible from chunks
+        assert!(from_slice::<&str>(&[0x7f, 0x61, 0x61, 0xff]).is_err());
+    }
+
+    mod serde_bytes_like {
+        use super::*;
+
+        #[derive(Debug)]
+        pub struct ByteBuf(pub Vec<u8>);
+
+        impl<'de> Deserialize<'de> for ByteBuf {
+            fn deserialize<D: de::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                struct V;
+                impl<'de> de::Visitor<'de> for V {
+                    type Value = ByteBuf;
+                    fn expecting(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                        f.write_str("bytes")
+                    }
+                    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<ByteBuf, E> {
+                        Ok(ByteBuf(v.to_vec()))
+                    }
+                    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<ByteBuf, E> {
+                        Ok(ByteBuf(v))
+                    }
+                }
+                d.deserialize_bytes(V)
+            }
+        }
+    }
+
+    #[test]
+    fn test_self_describe() {
+        let value: u8 = from_slice(&[0xd9, 0xd9, 0xf7, 0x01]).unwrap();
+        assert_eq!(1, value);
+        let value: (u8, u8) = from_slice_flat(&[0xd9, 0xd9, 0xf7, 0x01, 0x02]).unwrap();
+        assert_eq!((1, 2), value);
+        let cfg = Config::default().self_describe(true);
+        let data = crate::to_vec_cfg(&TestEnum::B(1), cfg).unwrap();
+        assert_eq!(TestEnum::B(1), from_slice(&data).unwrap());
+    }
+
+    #[test]
+    fn test_unsupported_tag() {
+        // 99(1)
+        let err = from_slice::<serde::de::IgnoredAny>(&[0xd8, 0x63, 0x01]).unwrap_err();
+        assert!(matches!(err.kind, error::ErrorKind::UnsupportedTag(99)));
+        assert!(err.to_string().contains("99"));
+    }
+
+    #[test]
+    fn test_tag_handlers() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        #[serde(untagged)]
+        enum Any {
+            Num(u64),
+            Pair(u64, String),
+            Map(BTreeMap<u64, String>),
+            Unit(()),
+        }
+        // [99("a"), 101("b")]
+        let data = [0x82, 0xd8, 0x63, 0x61, b'a', 0xd8, 0x65, 0x61, b'b'];
+        assert!(from_slice::<Vec<Any>>(&data).is_err());
+
+        let mut de = Deserializer::new(&data);
+        de.tag_handler(99, TagHandler::Seq);
+        de.tag_handler(101, TagHandler::Map);
+        let v = Vec::<Any>::deserialize(&mut de).unwrap();
+        let map = vec![(101, "b".to_string())].into_iter().collect();
+        assert_eq!(v, [Any::Pair(99, "a".to_string()), Any::Map(map)]);
+
+        // registered handlers come first, the default policy takes the rest
+        let cfg = Config::default().unknown_tags(TagHandler::Unit);
+        let mut de = Deserializer::new_with_config(&data, cfg);
+        de.tag_handler(1, TagHandler::Reject);
+        let v = Vec::<Any>::deserialize(&mut de).unwrap();
+        assert_eq!(v, [Any::Unit(()), Any::Unit(())]);
+        let mut de = Deserializer::new_with_config(&[0xc1, 0x01], cfg);
+        de.tag_handler(1, TagHandler::Reject);
+        let err = Any::deserialize(&mut de).unwrap_err();
+        assert!(matches!(err.kind, error::ErrorKind::UnsupportedTag(1)));
+
+        let cfg = Config::default().unknown_tags(TagHandler::Pass);
+        let mut de = Deserializer::new_with_config(&[0xd8, 0x63, 0xd8, 0x64, 0x05], cfg);
+        assert_eq!(Any::Num(5), Any::deserialize(&mut de).unwrap());
+    }
+
+    #[test]
+    fn test_packed() {
+        let expect = TestStruct2 {
+            a: {
+                let mut mp = BTreeMap::new();
+                mp.insert(1, 2);
+                mp
+            },
+            b: 3,
+        };
+        let data = crate::to_vec_cfg(&expect, Config::default().packed(true)).unwrap();
+        assert_eq!([0x82, 0xa1, 0x01, 0x02, 0x03], data.as_slice());
+        let value: TestStruct2 = from_slice(&data).unwrap();
+        assert_eq!(expect, value);
+    }
+
     #[test]
     fn test_flat() {
         let exp = (0x01u8, "a", 0xffi32);
@@ -780,6 +1873,7 @@ pub mod de_test {
         B(u8),
         C(TestStruct),
         D((u8, u8)),
+        E { a: u8 },
     }
     #[test]
     fn test_enum() {
@@ -791,5 +1885,32 @@ pub mod de_test {
         test_enum! { [0xBF, 0x61, 0x43, 0xa4, 0x61, 0x62, 0x02, 0x61, 0x61, 0x01, 0x61, 0x64, 0x04, 0x61, 0x63, 0x03, 0xFF],
         TestEnum::C(TestStruct{a: 1, b: 2, c: 3, d: 4});}
         test_enum! {     [0xA1, 0x61, 0x44, 0x82, 0x01, 0x02 ], TestEnum::D((1, 2));}
+        test_enum! { [0xA1, 0x61, 0x45, 0xA1, 0x61, 0x61, 0x01], TestEnum::E { a: 1 };}
+    }
+
+    #[test]
+    fn test_128bit() {
+        let value: u128 = from_slice(&[0x18, 0xff]).unwrap();
+        assert_eq!(0xff, value);
+        let value: i128 = from_slice(&[0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).unwrap();
+        assert_eq!(-0x1_0000_0000_0000_0000, value);
+        let value: i128 = from_slice(&[0xc3, 0x49, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(-0x1_0000_0000_0000_0001, value);
+        // leading zero bytes are tolerated
+        let value: u128 = from_slice(&[0xc2, 0x4a, 0x00, 0x01, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert_eq!(0x1_0000_0000_0000_0000, value);
+        assert!(from_slice::<u128>(&[0x20]).is_err());
+        let mut too_big = vec![0xc2u8, 0x50];
+        too_big.extend_from_slice(&[0xff; 16]);
+        assert!(from_slice::<i128>(&too_big).is_err());
+
+        for v in [0u128, 1, u64::MAX as u128, u64::MAX as u128 + 1, u128::MAX] {
+            let data = crate::to_vec(&v).unwrap();
+            assert_eq!(v, from_slice::<u128>(&data).unwrap());
+        }
+        for v in [0i128, -1, i64::MIN as i128, -0x1_0000_0000_0000_0001, i128::MIN, i128::MAX] {
+            let data = crate::to_vec(&v).unwrap();
+            assert_eq!(v, from_slice::<i128>(&data).unwrap());
+        }
     }
 }
@@ -0,0 +1,452 @@
+//! Decimal fractions, bigfloats and rationals, tags 4, 5 and 30.
+//!
+//! The submodules are meant for `#[serde(with = "...")]`:
+//!
+//! * `rust_decimal` for `rust_decimal::Decimal`, with the `rust_decimal` feature.
+//! * `bigdecimal` for `bigdecimal::BigDecimal`, with the `bigdecimal` feature.
+//!
+//! Both serialize as a decimal fraction, tag 4 over `[exponent, mantissa]` with the
+//! value `mantissa * 10^exponent`. A bigfloat (tag 5, `mantissa * 2^exponent`) or a
+//! rational (tag 30, `[numerator, denominator]`) is accepted when decoding, as long as
+//! the decimal type can hold its exact value.
+//!
+//! ```
+//! # #[cfg(feature = "rust_decimal")]
+//! # {
+//! use rust_decimal::Decimal;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Invoice {
+//!     #[serde(with = "minicbor_ser::decimal::rust_decimal")]
+//!     total: Decimal,
+//! }
+//!
+//! let data = minicbor_ser::to_vec(&Invoice { total: Decimal::new(27315, 2) }).unwrap();
+//! // 4([-2, 27315])
+//! assert_eq!(&data[7..], &[0xc4, 0x82, 0x21, 0x19, 0x6a, 0xb3]);
+//! # }
+//! ```
+
+#![cfg_attr(not(any(feature = "rust_decimal", feature = "bigdecimal")), allow(dead_code))]
+
+/// Decimal fraction (RFC 8949 §3.4.4).
+pub(crate) const DECIMAL: u64 = 4;
+/// Bigfloat (RFC 8949 §3.4.4).
+pub(crate) const BIGFLOAT: u64 = 5;
+/// Rational number, `[numerator, denominator]`.
+pub(crate) const RATIONAL: u64 = 30;
+
+/// The tags a decimal can be decoded from.
+struct ExpectedTags;
+
+impl serde::de::Expected for ExpectedTags {
+    fn fmt(&self, f: &mut crate::lib::fmt::Formatter) -> crate::lib::fmt::Result {
+        f.write_str("tag 4, 5 or 30")
+    }
+}
+
+/// `#[serde(with = "minicbor_ser::decimal::rust_decimal")]` for `rust_decimal::Decimal`.
+#[cfg(feature = "rust_decimal")]
+pub mod rust_decimal {
+    use super::{ExpectedTags, BIGFLOAT, DECIMAL, RATIONAL};
+    use crate::lib::*;
+    use crate::tag::Tagged;
+    use ::rust_decimal::Decimal;
+    use serde::de::{self, Deserialize, Deserializer, Unexpected};
+    use serde::ser::{Serialize, Serializer};
+
+    pub fn serialize<S>(d: &Decimal, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let exponent = -i64::from(d.scale());
+        Tagged::new(DECIMAL, (exponent, d.mantissa())).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let tagged = Tagged::<(i128, i128)>::deserialize(deserializer)?;
+        let (a, b) = tagged.value;
+        let d = match tagged.tag {
+            DECIMAL => i64::try_from(a).ok().and_then(|e| from_decimal(e, b)),
+            BIGFLOAT => i64::try_from(a).ok().and_then(|e| from_bigfloat(e, b)),
+            RATIONAL => from_rational(a, b),
+            tag => {
+                return Err(de::Error::invalid_value(
+                    Unexpected::Unsigned(tag),
+                    &ExpectedTags,
+                ))
+            }
+        };
+        d.ok_or_else(|| de::Error::custom("number can't be represented as a Decimal"))
+    }
+
+    /// `m * 10^e`
+    fn from_decimal(e: i64, mut m: i128) -> Option<Decimal> {
+        if m == 0 {
+            return Some(Decimal::ZERO);
+        }
+        if e >= 0 {
+            // the largest Decimal is below 10^29
+            if e > 28 {
+                return None;
+            }
+            let mut d = Decimal::try_from_i128_with_scale(m, 0).ok()?;
+            for _ in 0..e {
+                d = d.checked_mul(Decimal::TEN)?;
+            }
+            return Some(d);
+        }
+        let mut scale = e.unsigned_abs();
+        while scale > u64::from(Decimal::MAX_SCALE) && m % 10 == 0 {
+            m /= 10;
+            scale -= 1;
+        }
+        let scale = u32::try_from(scale).ok()?;
+        Decimal::try_from_i128_with_scale(m, scale).ok()
+    }
+
+    /// `m * 2^e`
+    fn from_bigfloat(e: i64, mut m: i128) -> Option<Decimal> {
+        if m == 0 {
+            return Some(Decimal::ZERO);
+        }
+        if e >= 0 {
+            if e > 96 {
+                return None;
+            }
+            let mut d = Decimal::try_from_i128_with_scale(m, 0).ok()?;
+            for _ in 0..e {
+                d = d.checked_mul(Decimal::TWO)?;
+            }
+            return Some(d);
+        }
+        // m * 2^-k = m * 5^k * 10^-k
+        let mut k = e.unsigned_abs();
+        while k > 0 && m % 2 == 0 {
+            m /= 2;
+            k -= 1;
+        }
+        for _ in 0..k {
+            m = m.checked_mul(5)?;
+        }
+        from_decimal(-i64::try_from(k).ok()?, m)
+    }
+
+    /// `n / d`, if the quotient is exact.
+    fn from_rational(n: i128, d: i128) -> Option<Decimal> {
+        if d <= 0 {
+            return None;
+        }
+        let n = Decimal::try_from_i128_with_scale(n, 0).ok()?;
+        let d = Decimal::try_from_i128_with_scale(d, 0).ok()?;
+        let q = n.checked_div(d)?;
+        if q.checked_mul(d)? != n {
+            return None;
+        }
+        Some(q.normalize())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_from_parts() {
+            assert_eq!(Some(Decimal::new(27315, 2)), from_decimal(-2, 27315));
+            assert_eq!(Some(Decimal::new(1500, 0)), from_decimal(2, 15));
+            assert_eq!(Some(Decimal::new(1, 28)), from_decimal(-30, 100));
+            assert_eq!(None, from_decimal(-29, 1));
+            assert_eq!(None, from_decimal(29, 1));
+
+            assert_eq!(Some(Decimal::new(15, 1)), from_bigfloat(-1, 3));
+            assert_eq!(Some(Decimal::new(-12, 0)), from_bigfloat(2, -3));
+            assert_eq!(Some(Decimal::new(1, 0)), from_bigfloat(-4, 16));
+
+            assert_eq!(Some(Decimal::new(25, 2)), from_rational(1, 4));
+            assert_eq!(None, from_rational(1, 3));
+            assert_eq!(None, from_rational(1, 0));
+        }
+    }
+}
+
+/// `#[serde(with = "minicbor_ser::decimal::bigdecimal")]` for `bigdecimal::BigDecimal`.
+#[cfg(feature = "bigdecimal")]
+pub mod bigdecimal {
+    use super::{ExpectedTags, BIGFLOAT, DECIMAL, RATIONAL};
+    use crate::lib::*;
+    use crate::tag::Tagged;
+    use ::bigdecimal::num_bigint::{BigInt, Sign};
+    use ::bigdecimal::{BigDecimal, One, ToPrimitive, Zero};
+    use serde::de::{self, Deserialize, Deserializer, SeqAccess, Unexpected, Visitor};
+    use serde::ser::{Serialize, Serializer};
+
+    /// The largest bigfloat exponent that is expanded, to bound the work.
+    const MAX_EXPONENT: u64 = 1 << 16;
+
+    pub fn serialize<S>(d: &BigDecimal, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let (m, scale) = d.as_bigint_and_exponent();
+        let exponent = scale
+            .checked_neg()
+            .ok_or_else(|| serde::ser::Error::custom("decimal scale out of range"))?;
+        Tagged::new(DECIMAL, (exponent, Mantissa(m))).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<BigDecimal, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let tagged = Tagged::<(Mantissa, Mantissa)>::deserialize(deserializer)?;
+        let (Mantissa(a), Mantissa(b)) = tagged.value;
+        let d = match tagged.tag {
+            DECIMAL => a
+                .to_i64()
+                .and_then(i64::checked_neg)
+                .map(|scale| BigDecimal::new(b, scale)),
+            BIGFLOAT => a.to_i64().and_then(|e| from_bigfloat(e, b)),
+            RATIONAL => from_rational(a, b),
+            tag => {
+                return Err(de::Error::invalid_value(
+                    Unexpected::Unsigned(tag),
+                    &ExpectedTags,
+                ))
+            }
+        };
+        d.ok_or_else(|| de::Error::custom("number can't be represented as a BigDecimal"))
+    }
+
+    /// `m * 2^e`
+    fn from_bigfloat(e: i64, m: BigInt) -> Option<BigDecimal> {
+        if e.unsigned_abs() > MAX_EXPONENT {
+            return None;
+        }
+        if e >= 0 {
+            return Some(BigDecimal::new(m << e as usize, 0));
+        }
+        // m * 2^-k = m * 5^k * 10^-k
+        let k = e.unsigned_abs() as u32;
+        Some(BigDecimal::new(m * BigInt::from(5u8).pow(k), i64::from(k)))
+    }
+
+    /// `n / d`, if the quotient is exact.
+    fn from_rational(n: BigInt, d: BigInt) -> Option<BigDecimal> {
+        if d.sign() != Sign::Plus {
+            return None;
+        }
+        // the quotient terminates only if `d` has no prime factors but 2 and 5
+        let mut rest = d.clone();
+        let mut twos = 0u64;
+        let mut fives = 0u64;
+        let (two, five) = (BigInt::from(2u8), BigInt::from(5u8));
+        while (&rest % &two).is_zero() {
+            rest /= &two;
+            twos += 1;
+        }
+        while (&rest % &five).is_zero() {
+            rest /= &five;
+            fives += 1;
+        }
+        if !rest.is_one() {
+            return None;
+        }
+        // n / (2^a * 5^b) = n * 2^(k-a) * 5^(k-b) / 10^k
+        let k = twos.max(fives);
+        if k > MAX_EXPONENT {
+            return None;
+        }
+        let m = n * two.pow((k - twos) as u32) * five.pow((k - fives) as u32);
+        Some(BigDecimal::new(m, k as i64))
+    }
+
+    /// An integer of any size, a bignum if it doesn't fit 128 bits.
+    struct Mantissa(BigInt);
+
+    impl Serialize for Mantissa {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            if let Some(n) = self.0.to_i128() {
+                return serializer.serialize_i128(n);
+            }
+            let (tag, m) = match self.0.sign() {
+                Sign::Minus => (3, -&self.0 - 1u8),
+                _ => (2, self.0.clone()),
+            };
+            let (_, bytes) = m.to_bytes_be();
+            Tagged::new(tag, Bytes(bytes)).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Mantissa {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_any(MantissaVisitor)
+        }
+    }
+
+    struct MantissaVisitor;
+
+    impl<'de> Visitor<'de> for MantissaVisitor {
+        type Value = Mantissa;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an integer or a bignum")
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+            Ok(Mantissa(v.into()))
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+            Ok(Mantissa(v.into()))
+        }
+
+        fn visit_i128<E: de::Error>(self, v: i128) -> Result<Self::Value, E> {
+            Ok(Mantissa(v.into()))
+        }
+
+        fn visit_u128<E: de::Error>(self, v: u128) -> Result<Self::Value, E> {
+            Ok(Mantissa(v.into()))
+        }
+
+        /// A bignum wider than 128 bits, `[tag, bytes]`.
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let tag: u64 = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            let Bytes(bytes) = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+            let m = BigInt::from_bytes_be(Sign::Plus, &bytes);
+            match tag {
+                2 => Ok(Mantissa(m)),
+                3 => Ok(Mantissa(-m - 1u8)),
+                _ => Err(de::Error::invalid_value(Unexpected::Unsigned(tag), &self)),
+            }
+        }
+    }
+
+    struct Bytes(Vec<u8>);
+
+    impl Serialize for Bytes {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Bytes {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_byte_buf(BytesVisitor)
+        }
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Bytes;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("bytes")
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            Ok(Bytes(v.to_vec()))
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+            Ok(Bytes(v))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use core::str::FromStr;
+
+        #[test]
+        fn test_from_parts() {
+            let d = |s| BigDecimal::from_str(s).unwrap();
+            assert_eq!(Some(d("1.5")), from_bigfloat(-1, 3.into()));
+            assert_eq!(Some(d("-12")), from_bigfloat(2, (-3).into()));
+            assert_eq!(Some(d("0.25")), from_rational(1.into(), 4.into()));
+            assert_eq!(Some(d("-0.3")), from_rational((-3).into(), 10.into()));
+            assert_eq!(None, from_rational(1.into(), 3.into()));
+            assert_eq!(None, from_rational(1.into(), 0.into()));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "rust_decimal", feature = "bigdecimal"))]
+mod tests {
+    use crate::{from_slice, to_vec};
+    use ::bigdecimal::BigDecimal;
+    use ::rust_decimal::Decimal;
+    use core::str::FromStr;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Small(#[serde(with = "super::rust_decimal")] Decimal);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Big(#[serde(with = "super::bigdecimal")] BigDecimal);
+
+    #[test]
+    fn test_decimal() {
+        let data = to_vec(&Small(Decimal::new(27315, 2))).unwrap();
+        assert_eq!(data, [0xc4, 0x82, 0x21, 0x19, 0x6a, 0xb3]);
+        assert_eq!(Small(Decimal::new(27315, 2)), from_slice(&data).unwrap());
+        let big: Big = from_slice(&data).unwrap();
+        assert_eq!(big.0, BigDecimal::from_str("273.15").unwrap());
+        assert_eq!(data, to_vec(&big).unwrap());
+
+        // 5([-1, 3]) and 30([1, 4])
+        let v: Small = from_slice(&[0xc5, 0x82, 0x20, 0x03]).unwrap();
+        assert_eq!(v.0, Decimal::new(15, 1));
+        let v: Big = from_slice(&[0xd8, 0x1e, 0x82, 0x01, 0x04]).unwrap();
+        assert_eq!(v.0, BigDecimal::from_str("0.25").unwrap());
+
+        // the mantissa of `Decimal::MAX` exceeds 64 bits
+        let data = to_vec(&Small(Decimal::MAX)).unwrap();
+        assert_eq!(&data[..4], [0xc4, 0x82, 0x00, 0xc2]);
+        assert_eq!(Small(Decimal::MAX), from_slice(&data).unwrap());
+
+        // a rational numerator beyond 64 bits, 2^70 / 4
+        let data = to_vec(&crate::Tagged::new(30, (1i128 << 70, 4))).unwrap();
+        assert_eq!(Small(Decimal::from(1u128 << 68)), from_slice(&data).unwrap());
+        let data = to_vec(&crate::Tagged::new(4, (1i128 << 70, 1))).unwrap();
+        assert!(from_slice::<Small>(&data).is_err());
+
+        let err = from_slice::<Small>(&[0xc6, 0x82, 0x20, 0x03]).unwrap_err();
+        assert!(err.to_string().contains("6"), "{}", err);
+    }
+
+    #[test]
+    fn test_bigdecimal() {
+        for s in ["-1e-40", "123456789012345678901234567890123456789012.5", "-0.000001"] {
+            let v = Big(BigDecimal::from_str(s).unwrap());
+            let data = to_vec(&v).unwrap();
+            assert_eq!(v, from_slice(&data).unwrap(), "{}", s);
+        }
+        // too many digits for a Decimal
+        let data = to_vec(&Big(BigDecimal::from_str("1e-40").unwrap())).unwrap();
+        assert!(from_slice::<Small>(&data).is_err());
+    }
+}
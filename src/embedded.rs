@@ -0,0 +1,208 @@
+//! Embedded CBOR data items, tag 24.
+//!
+//! [`Embedded`] keeps an encoded item byte for byte next to its decoded
+//! value, so that signed or hashed data survives a roundtrip unchanged.
+//!
+//! ```
+//! use minicbor_ser::embedded::Embedded;
+//! use minicbor_ser::{from_slice, to_vec};
+//!
+//! let item = Embedded::new((1u8, "a")).unwrap();
+//! assert_eq!(item.as_bytes(), [0x82, 0x01, 0x61, 0x61]);
+//!
+//! let data = to_vec(&item).unwrap();
+//! assert_eq!(&data[..3], [0xd8, 0x18, 0x44]);
+//! let item: Embedded<(u8, String)> = from_slice(&data).unwrap();
+//! assert_eq!(item.get().1, "a");
+//! ```
+
+use crate::error::de::{make_kind_err, Error as DeError, ErrorKind};
+use crate::error::en::Error as EnError;
+use crate::lib::*;
+use crate::tag::Tag;
+use serde::de::{self, DeserializeOwned, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+/// Encoded CBOR data item, over a byte string.
+pub(crate) const EMBEDDED: u64 = 24;
+
+/// A data item written as tag 24 over its own encoding.
+///
+/// The raw bytes are kept as they were read and written back unchanged,
+/// the value is decoded from them eagerly.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Embedded<T> {
+    raw: Vec<u8>,
+    value: T,
+}
+
+impl<T: Serialize> Embedded<T> {
+    /// Encode `value` with the default [`Config`](crate::Config).
+    pub fn new(value: T) -> Result<Self, EnError> {
+        let raw = crate::to_vec(&value)?;
+        Ok(Embedded { raw, value })
+    }
+}
+
+impl<T: DeserializeOwned> Embedded<T> {
+    /// Decode an item from its encoding, keeping the bytes.
+    ///
+    /// The bytes must hold exactly one data item.
+    pub fn from_bytes(raw: Vec<u8>) -> Result<Self, DeError> {
+        let mut de = crate::de::Deserializer::new(&raw);
+        let value = T::deserialize(&mut de)?;
+        if de.decoder().position() != raw.len() {
+            return Err(make_kind_err(
+                ErrorKind::TrailingData,
+                "embedded data item is followed by more data",
+            ));
+        }
+        Ok(Embedded { raw, value })
+    }
+}
+
+impl<T> Embedded<T> {
+    /// The encoded item.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.raw
+    }
+
+    /// The decoded item.
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    pub fn into_parts(self) -> (Vec<u8>, T) {
+        (self.raw, self.value)
+    }
+}
+
+impl<T> Serialize for Embedded<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Tag::<EMBEDDED, _>(Raw(&self.raw)).serialize(serializer)
+    }
+}
+
+impl<'de, T: DeserializeOwned> de::Deserialize<'de> for Embedded<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let Tag(RawBuf(raw)) = Tag::<EMBEDDED, RawBuf>::deserialize(deserializer)?;
+        Embedded::from_bytes(raw).map_err(de::Error::custom)
+    }
+}
+
+struct Raw<'a>(&'a [u8]);
+
+impl Serialize for Raw<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+struct RawBuf(Vec<u8>);
+
+impl<'de> de::Deserialize<'de> for RawBuf {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_byte_buf(RawVisitor)
+    }
+}
+
+struct RawVisitor;
+
+impl<'de> Visitor<'de> for RawVisitor {
+    type Value = RawBuf;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an encoded data item")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(RawBuf(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(RawBuf(v))
+    }
+
+    /// Formats without byte strings write bytes as a sequence.
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut raw = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(b) = seq.next_element()? {
+            raw.push(b);
+        }
+        Ok(RawBuf(raw))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_slice, to_vec};
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Signed {
+        payload: Embedded<BTreeMap<String, u32>>,
+        signature: u8,
+    }
+
+    #[test]
+    fn test_embedded() {
+        let item = Embedded::<u32>::new(500).unwrap();
+        assert_eq!(item.as_bytes(), [0x19, 0x01, 0xf4]);
+        let data = to_vec(&item).unwrap();
+        assert_eq!(data, [0xd8, 0x18, 0x43, 0x19, 0x01, 0xf4]);
+        assert_eq!(item, from_slice(&data).unwrap());
+
+        // the tag is required, the bytes must hold a whole item
+        assert!(from_slice::<Embedded<u32>>(&data[2..]).is_err());
+        assert!(from_slice::<Embedded<u32>>(&[0xd8, 0x18, 0x42, 0x19, 0x01]).is_err());
+        assert!(from_slice::<Embedded<u32>>(&[0xd8, 0x19, 0x43, 0x19, 0x01, 0xf4]).is_err());
+        // and nothing after it
+        assert!(from_slice::<Embedded<u8>>(&[0xd8, 0x18, 0x42, 0x01, 0x02]).is_err());
+        assert!(Embedded::<u8>::from_bytes(vec![0x01, 0x02]).is_err());
+    }
+
+    #[test]
+    fn test_raw_kept() {
+        // {"a": 1} with a non-preferred length for 1
+        let payload = vec![0xa1, 0x61, 0x61, 0x18, 0x01];
+        let signed = Signed {
+            payload: Embedded::from_bytes(payload.clone()).unwrap(),
+            signature: 7,
+        };
+        assert_eq!(signed.payload.get()["a"], 1);
+
+        let data = to_vec(&signed).unwrap();
+        let back: Signed = from_slice(&data).unwrap();
+        assert_eq!(back.payload.as_bytes(), payload);
+        assert_eq!(back, signed);
+        assert_eq!(back.payload.into_parts().0, payload);
+    }
+}
@@ -40,7 +40,6 @@ pub mod en {
         Message,
         Custom,
         Unknow,
-        Unsupported128BitInteger,
     }
     impl Display for ErrorKind {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -49,7 +48,6 @@ pub mod en {
                 ErrorKind::Message => write!(f, "Message"),
                 ErrorKind::Custom => write!(f, "Custom"),
                 ErrorKind::Unknow => write!(f, "Unknow"),
-                ErrorKind::Unsupported128BitInteger => write!(f, "Unsupported128BitIntege"),
             }
         }
     }
@@ -167,8 +165,16 @@ pub mod de {
         UnknownVariant,
         /// A value was missing at the specified index.
         MissingValue,
-        /// 128-bit integers are not supported at this time
-        Unsupported128BitInteger,
+        /// An integer does not fit into the requested type.
+        IntegerOverflow,
+        /// A tag this crate can't interpret.
+        UnsupportedTag(u64),
+        /// A set contains the same element twice.
+        DuplicateElement,
+        /// Data follows an item that must be the whole input.
+        TrailingData,
+        /// A string reference (tag 25) to a string that wasn't indexed.
+        UnknownReference,
 
         Custom,
         Unknow,
@@ -189,7 +195,11 @@ pub mod de {
                 }
                 ErrorKind::UnknownVariant => write!(f, "UnknownVariant"),
                 ErrorKind::MissingValue => write!(f, "MissingValue"),
-                ErrorKind::Unsupported128BitInteger => write!(f, "Unsupported128BitInteger"),
+                ErrorKind::IntegerOverflow => write!(f, "IntegerOverflow"),
+                ErrorKind::UnsupportedTag(tag) => write!(f, "UnsupportedTag{{ {} }}", tag),
+                ErrorKind::DuplicateElement => write!(f, "DuplicateElement"),
+                ErrorKind::TrailingData => write!(f, "TrailingData"),
+                ErrorKind::UnknownReference => write!(f, "UnknownReference"),
                 ErrorKind::Custom => write!(f, "Custom"),
                 ErrorKind::Unknow => write!(f, "Unknow"),
             }
@@ -264,6 +274,14 @@ pub mod de {
         }
     }
     #[inline]
+    pub(crate) fn unsupported_tag(tag: u64) -> Error {
+        Error {
+            source: None,
+            kind: ErrorKind::UnsupportedTag(tag),
+            msg: make_msg("tag is not supported"),
+        }
+    }
+    #[inline]
     pub(crate) fn type_mismatch(t: Type, s: &'static str) -> Error {
         Error {
             source: None,
@@ -0,0 +1,157 @@
+//! Conversions between the CBOR float widths, and half float support for `half::f16`.
+
+/// Widen the bits of a half float (`f16`) to an `f32`.
+pub(crate) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = u32::from(bits & 0x8000) << 16;
+    let exp = u32::from((bits >> 10) & 0x1f);
+    let man = u32::from(bits & 0x3ff);
+    match exp {
+        // zero and subnormal values are `man * 2^-24`
+        0 => {
+            let v = man as f32 / 16_777_216.0;
+            if sign == 0 {
+                v
+            } else {
+                -v
+            }
+        }
+        // infinity and NaN
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (man << 13)),
+        _ => f32::from_bits(sign | ((exp + 112) << 23) | (man << 13)),
+    }
+}
+
+/// Narrow `x` to the bits of a half float, if that loses nothing.
+pub(crate) fn f32_to_f16(x: f32) -> Option<u16> {
+    let bits = x.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exp = ((bits >> 23) & 0xff) as i32;
+    let man = bits & 0x7f_ffff;
+    if exp == 0xff {
+        // infinity and NaN, the payload has to survive
+        if man & 0x1fff != 0 {
+            return None;
+        }
+        return Some(sign | 0x7c00 | (man >> 13) as u16);
+    }
+    if exp == 0 {
+        // f32 subnormals are too small for a half float
+        return if man == 0 { Some(sign) } else { None };
+    }
+    let e = exp - 127;
+    if (-14..=15).contains(&e) {
+        if man & 0x1fff != 0 {
+            return None;
+        }
+        return Some(sign | (((e + 15) as u16) << 10) | (man >> 13) as u16);
+    }
+    if (-24..-14).contains(&e) {
+        // half subnormal `m * 2^-24`
+        let full = man | 0x80_0000;
+        let shift = (-1 - e) as u32;
+        if full & ((1 << shift) - 1) != 0 {
+            return None;
+        }
+        return Some(sign | (full >> shift) as u16);
+    }
+    None
+}
+
+/// Narrow `x` to an `f32`, if that loses nothing.
+pub(crate) fn f64_to_f32(x: f64) -> Option<f32> {
+    if x.is_nan() {
+        let bits = x.to_bits();
+        if bits & 0x1fff_ffff != 0 {
+            return None;
+        }
+        let sign = ((bits >> 32) & 0x8000_0000) as u32;
+        let man = ((bits >> 29) & 0x7f_ffff) as u32;
+        return Some(f32::from_bits(sign | 0x7f80_0000 | man));
+    }
+    let y = x as f32;
+    if f64::from(y) == x {
+        Some(y)
+    } else {
+        None
+    }
+}
+
+/// Write a `half::f16` as a CBOR half float.
+///
+/// Use it with `#[serde(with = "minicbor_ser::float::half")]`. Half, single and double floats
+/// are accepted when decoding, as are the bits as an integer.
+#[cfg(feature = "half")]
+pub mod half {
+    use crate::ser::HALF_NAME;
+    use ::half::f16;
+    use core::fmt;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(value: &f16, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(HALF_NAME, &value.to_bits())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<f16, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(HALF_NAME, Visitor)
+    }
+
+    struct Visitor;
+
+    impl<'de> de::Visitor<'de> for Visitor {
+        type Value = f16;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a half float")
+        }
+
+        fn visit_f32<E>(self, v: f32) -> Result<f16, E> {
+            Ok(f16::from_f32(v))
+        }
+
+        fn visit_f64<E>(self, v: f64) -> Result<f16, E> {
+            Ok(f16::from_f64(v))
+        }
+
+        fn visit_newtype_struct<D>(self, deserializer: D) -> Result<f16, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            u16::deserialize(deserializer).map(f16::from_bits)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_f16() {
+        for bits in 0..=u16::MAX {
+            let x = f16_to_f32(bits);
+            if x.is_nan() {
+                assert_eq!(bits & 0x7c00, 0x7c00);
+            }
+            assert_eq!(Some(bits), f32_to_f16(x), "bits: {:#x}", bits);
+        }
+        assert_eq!(65504.0, f16_to_f32(0x7bff));
+        assert_eq!(5.960_464_5e-8, f16_to_f32(0x0001));
+        assert_eq!(None, f32_to_f16(0.1));
+        assert_eq!(None, f32_to_f16(65536.0));
+        assert_eq!(None, f32_to_f16(1.0e-10));
+    }
+
+    #[test]
+    fn test_f32() {
+        assert_eq!(Some(1.5), f64_to_f32(1.5));
+        assert_eq!(None, f64_to_f32(0.1));
+        assert_eq!(None, f64_to_f32(1.0e300));
+        assert!(f64_to_f32(f64::NAN).unwrap().is_nan());
+    }
+}
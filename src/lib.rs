@@ -9,6 +9,7 @@
 //!
 //! ```rust
 //! use serde::Serialize;
+//! use minicbor_ser::to_vec;
 //! #[derive(Debug, Serialize)]
 //! struct TestStruct {
 //!    hello: String,
@@ -29,6 +30,7 @@
 //!
 //! ```rust
 //! use serde::Deserialize;
+//! use minicbor_ser::from_slice;
 //! #[derive(Debug, Deserialize, PartialEq)]
 //! struct TestStruct {
 //!     hello: String,
@@ -50,11 +52,33 @@
 #[cfg(all(feature = "alloc", not(feature = "std")))]
 extern crate alloc;
 
+pub mod datetime;
 pub mod de;
+pub mod decimal;
+#[cfg(feature = "alloc")]
+pub mod embedded;
 pub mod error;
+pub mod float;
+pub mod net;
+#[cfg(feature = "ndarray")]
+pub mod ndarray;
 pub mod ser;
+pub mod set;
+#[cfg(feature = "std")]
+pub mod shared;
+mod stringref;
+pub mod tag;
+pub mod text;
+pub mod typed_array;
+#[cfg(feature = "alloc")]
+pub mod value;
+#[cfg(feature = "url")]
+pub mod url;
+#[cfg(feature = "uuid")]
+pub mod uuid;
 pub use minicbor as cbor;
 
+#[allow(unused_imports)]
 mod lib {
     mod core {
         #[cfg(not(feature = "std"))]
@@ -66,7 +90,7 @@ mod lib {
 
     pub use self::core::cell::{Cell, RefCell};
     pub use self::core::clone::{self, Clone};
-    pub use self::core::convert::{self, From, Into};
+    pub use self::core::convert::{self, From, Into, TryFrom};
     pub use self::core::default::{self, Default};
     pub use self::core::fmt::{self, Debug, Display};
     pub use self::core::hash::{self, Hash};
@@ -74,7 +98,7 @@ mod lib {
     pub use self::core::marker::{self, PhantomData};
     pub use self::core::ops::{Bound, RangeBounds};
     pub use self::core::result::{self, Result};
-    pub use self::core::{borrow, char, cmp, iter, mem, num, ops, slice, str};
+    pub use self::core::{borrow, char, cmp, iter, mem, net, num, ops, slice, str};
 
     #[cfg(all(feature = "alloc", not(feature = "std")))]
     pub use alloc::string::{String, ToString};
@@ -92,24 +116,251 @@ mod lib {
     pub use std::boxed::Box;
 
     #[cfg(all(feature = "alloc", not(feature = "std")))]
-    pub use alloc::collections::{btree_map, BTreeMap};
+    pub use alloc::borrow::Cow;
     #[cfg(feature = "std")]
-    pub use std::collections::{btree_map, BTreeMap};
+    pub use std::borrow::Cow;
+
+    #[cfg(all(feature = "alloc", not(feature = "std")))]
+    pub use alloc::collections::{btree_map, BTreeMap, BTreeSet};
+    #[cfg(feature = "std")]
+    pub use std::collections::{btree_map, BTreeMap, BTreeSet};
 }
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Config {
     top_flatten: bool,
+    packed: bool,
+    variant_index: bool,
+    enum_repr: EnumRepr,
+    preferred_float: bool,
+    self_describe: bool,
+    compact: bool,
+    unique_sets: bool,
+    date_time: DateTimeRepr,
+    date: DateRepr,
+    unknown_tags: TagHandler,
+    #[cfg(feature = "alloc")]
+    string_refs: bool,
+    #[cfg(feature = "std")]
+    shared_values: bool,
+    #[cfg(feature = "std")]
+    max_shared_values: Option<usize>,
+    #[cfg(feature = "alloc")]
+    deterministic: Option<KeyOrder>,
+}
+
+impl Config {
+    /// Don't wrap top-level `struct` and `tuple` with `map` and `array`.
+    pub fn top_flatten(mut self, flatten: bool) -> Self {
+        self.top_flatten = flatten;
+        self
+    }
+
+    /// Serialize structs and struct variants as arrays of their field values, in declaration order.
+    ///
+    /// Field names are not written, so both sides must agree on the field order.
+    pub fn packed(mut self, packed: bool) -> Self {
+        self.packed = packed;
+        self
+    }
+
+    /// Identify enum variants by their index instead of their name.
+    pub fn variant_index(mut self, index: bool) -> Self {
+        self.variant_index = index;
+        self
+    }
+
+    /// Choose how enum variants are laid out, see [`EnumRepr`].
+    ///
+    /// Both representations are accepted when decoding.
+    pub fn enum_repr(mut self, repr: EnumRepr) -> Self {
+        self.enum_repr = repr;
+        self
+    }
+
+    /// Write each float at the narrowest width (`f16`, `f32` or `f64`) that keeps its exact value.
+    ///
+    /// This is the preferred serialization of RFC 8949 §4.1 and is implied by [`Config::deterministic`].
+    pub fn preferred_float(mut self, preferred: bool) -> Self {
+        self.preferred_float = preferred;
+        self
+    }
+
+    /// Prefix the output with the self-describe tag 55799, so that tools can recognize it as CBOR.
+    ///
+    /// The tag is written by [`to_writer_cfg`] and [`to_vec_cfg`], a [`ser::Serializer`]
+    /// created with this config doesn't write it. A leading self-describe tag is always
+    /// skipped when decoding.
+    pub fn self_describe(mut self, self_describe: bool) -> Self {
+        self.self_describe = self_describe;
+        self
+    }
+
+    /// Whether serde types should use their human readable form, `true` by default.
+    ///
+    /// When `false`, types with a compact form use it, e.g. `IpAddr` is written as
+    /// tag 52 or 54 over its octets (RFC 9164) instead of text.
+    pub fn human_readable(mut self, readable: bool) -> Self {
+        self.compact = !readable;
+        self
+    }
+
+    /// Reject sets (tag 258) that contain the same element twice when decoding.
+    ///
+    /// Elements are compared by their encoding.
+    pub fn unique_sets(mut self, unique: bool) -> Self {
+        self.unique_sets = unique;
+        self
+    }
+
+    /// Choose how date and time values are written, see [`DateTimeRepr`] and [`datetime`].
+    ///
+    /// Both representations are accepted when decoding.
+    pub fn date_time(mut self, repr: DateTimeRepr) -> Self {
+        self.date_time = repr;
+        self
+    }
+
+    /// Choose how calendar dates are written, see [`DateRepr`] and [`datetime`].
+    ///
+    /// Both representations are accepted when decoding.
+    pub fn date(mut self, repr: DateRepr) -> Self {
+        self.date = repr;
+        self
+    }
+
+    /// Choose what `deserialize_any` does with tags it doesn't know, see [`TagHandler`].
+    ///
+    /// Handlers for single tags are registered with [`de::Deserializer::tag_handler`].
+    pub fn unknown_tags(mut self, handler: TagHandler) -> Self {
+        self.unknown_tags = handler;
+        self
+    }
+
+    /// Write repeated text and byte strings as references (tag 25), in a namespace (tag 256)
+    /// around the output.
+    ///
+    /// Only [`to_writer_cfg`] and [`to_vec_cfg`] write references, as the namespace must come
+    /// first; a [`ser::Serializer`] created with this config doesn't. This has no effect in
+    /// deterministic mode. References are always resolved when decoding.
+    #[cfg(feature = "alloc")]
+    pub fn string_refs(mut self, refs: bool) -> Self {
+        self.string_refs = refs;
+        self
+    }
+
+    /// Write the first occurrence of each [`shared::Shared`] pointer as a shareable value
+    /// (tag 28) and later occurrences as references to it (tag 29). Pointers that occur
+    /// once are tagged too, as the encoder doesn't look ahead.
+    ///
+    /// This has no effect in deterministic mode. Shared values are always rebuilt when decoding.
+    #[cfg(feature = "std")]
+    pub fn shared_values(mut self, shared: bool) -> Self {
+        self.shared_values = shared;
+        self
+    }
+
+    /// Limit the number of shared values (tag 28) a document may create when decoding,
+    /// 65536 by default.
+    #[cfg(feature = "std")]
+    pub fn max_shared_values(mut self, max: usize) -> Self {
+        self.max_shared_values = Some(max);
+        self
+    }
+
+    /// Enable the core deterministic encoding of RFC 8949 §4.2.
+    ///
+    /// Map keys are sorted by `order` and every item is written with a definite length.
+    /// Integer, length and tag heads always use the shortest form, as do floats.
+    #[cfg(feature = "alloc")]
+    pub fn deterministic(mut self, order: KeyOrder) -> Self {
+        self.deterministic = Some(order);
+        self
+    }
+}
+
+/// The layout of enum variants, see [`Config::enum_repr`].
+///
+/// The discriminant is the variant name, or its index with [`Config::variant_index`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EnumRepr {
+    /// `{variant: payload}`, unit variants are written as the bare discriminant.
+    #[default]
+    Map,
+    /// `[variant, field1, field2, ...]`, as written by minicbor's derive.
+    Array,
+}
+
+/// The representation of date and time values, see [`Config::date_time`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateTimeRepr {
+    /// RFC 3339 text in tag 0, e.g. `0("2013-03-21T20:04:00Z")`.
+    #[default]
+    Text,
+    /// Seconds since the epoch in tag 1, e.g. `1(1363896240)`.
+    ///
+    /// The seconds are an integer, or a float if there is a fractional part.
+    Epoch,
+}
+
+/// The representation of calendar dates, see [`Config::date`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DateRepr {
+    /// RFC 3339 full-date text in tag 1004, e.g. `1004("2013-03-21")`.
+    #[default]
+    Text,
+    /// Days since 1970-01-01 in tag 100, e.g. `100(15785)`.
+    Days,
+}
+
+/// What `deserialize_any` does with a tag, for self-describing targets like untagged enums,
+/// see [`Config::unknown_tags`] and [`de::Deserializer::tag_handler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TagHandler {
+    /// Fail with [`ErrorKind::UnsupportedTag`](error::de::ErrorKind::UnsupportedTag).
+    #[default]
+    Reject,
+    /// Drop the tag and visit the enclosed value on its own.
+    Pass,
+    /// Visit `[tag, value]` as a sequence, as [`tag::Tagged`] reads it.
+    Seq,
+    /// Visit `{tag: value}` as a map with one entry.
+    Map,
+    /// Skip the enclosed value and visit a unit.
+    Unit,
+}
+
+/// The order of map keys in deterministic mode, see [`Config::deterministic`].
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// Bytewise lexicographic order of the encoded keys (RFC 8949 §4.2.1).
+    Lexicographic,
+    /// Shorter encoded keys first, then bytewise order (RFC 7049 §3.9, CTAP2).
+    LengthFirst,
+}
+
+#[cfg(feature = "alloc")]
+impl KeyOrder {
+    pub(crate) fn compare(&self, a: &[u8], b: &[u8]) -> lib::cmp::Ordering {
+        match self {
+            KeyOrder::Lexicographic => a.cmp(b),
+            KeyOrder::LengthFirst => a.len().cmp(&b.len()).then_with(|| a.cmp(b)),
+        }
+    }
 }
 
 pub use de::from_slice;
 pub use de::from_slice_flat;
 pub use ser::to_writer;
 pub use ser::to_writer_cfg;
+pub use tag::{Tag, Tagged};
 
 #[cfg(feature = "alloc")]
 pub use ser::to_vec;
 #[cfg(feature = "alloc")]
+pub use ser::to_vec_cfg;
+#[cfg(feature = "alloc")]
 pub use ser::to_vec_flat;
 
 #[test]
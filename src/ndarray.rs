@@ -0,0 +1,354 @@
+//! `#[serde(with = "minicbor_ser::ndarray")]` for `ndarray::Array`, tags 40
+//! and 1040.
+//!
+//! An array is written as tag 40 over `[[dims...], data]` (RFC 8746), with
+//! the elements in row-major order. The data is a
+//! [typed array](crate::typed_array) for numeric elements and a plain array
+//! otherwise. Row-major (tag 40) and column-major (tag 1040) arrays are
+//! accepted when decoding.
+//!
+//! ```
+//! use ndarray::{Array2, Ix2};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Layer {
+//!     #[serde(with = "minicbor_ser::ndarray")]
+//!     weights: Array2<f32>,
+//! }
+//! ```
+
+use crate::lib::*;
+use crate::tag::Tagged;
+use crate::typed_array::TypedArray;
+use ::ndarray::{Array, ArrayBase, Data, Dimension, IxDyn, ShapeBuilder};
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer, Unexpected};
+use serde::ser::{self, Serialize, Serializer};
+
+/// Multi-dimensional array in row-major order.
+pub(crate) const ROW_MAJOR: u64 = 40;
+/// Multi-dimensional array in column-major order.
+pub(crate) const COLUMN_MAJOR: u64 = 1040;
+
+pub fn serialize<A, S, D, Ser>(
+    array: &ArrayBase<S, D>,
+    serializer: Ser,
+) -> Result<Ser::Ok, Ser::Error>
+where
+    A: Serialize,
+    S: Data<Elem = A>,
+    D: Dimension,
+    Ser: Serializer,
+{
+    // only views that are not in standard layout need their elements gathered
+    match array.as_slice() {
+        Some(data) => Tagged::new(ROW_MAJOR, (array.shape(), Elements(data))).serialize(serializer),
+        None => {
+            let data: Vec<&A> = array.iter().collect();
+            Tagged::new(ROW_MAJOR, (array.shape(), Elements(&data[..]))).serialize(serializer)
+        }
+    }
+}
+
+pub fn deserialize<'de, A, D, De>(deserializer: De) -> Result<Array<A, D>, De::Error>
+where
+    A: DeserializeOwned,
+    D: Dimension,
+    De: Deserializer<'de>,
+{
+    let Tagged {
+        tag,
+        value: (dims, data),
+    } = Tagged::<(Vec<usize>, Vec<A>)>::deserialize(deserializer)?;
+    let array = match tag {
+        ROW_MAJOR => Array::from_shape_vec(IxDyn(&dims), data),
+        COLUMN_MAJOR => Array::from_shape_vec(IxDyn(&dims).f(), data),
+        _ => {
+            return Err(de::Error::invalid_value(
+                Unexpected::Unsigned(tag),
+                &"tag 40 or 1040",
+            ))
+        }
+    };
+    array
+        .map_err(de::Error::custom)?
+        .into_dimensionality()
+        .map_err(|_| de::Error::invalid_length(dims.len(), &"the dimensions of the array"))
+}
+
+/// The elements, as a typed array if they all serialize as the same kind of
+/// number.
+struct Elements<'a, A>(&'a [A]);
+
+impl<A: Serialize> Serialize for Elements<'_, A> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        macro_rules! typed {
+            ($($variant:ident($t:ty)),*) => {
+                match self.0.first().map(|a| a.serialize(NumberSerializer)) {
+                    $(Some(Ok(Number::$variant(_))) => {
+                        let data: Option<Vec<$t>> = self
+                            .0
+                            .iter()
+                            .map(|a| match a.serialize(NumberSerializer) {
+                                Ok(Number::$variant(n)) => Some(n),
+                                _ => None,
+                            })
+                            .collect();
+                        if let Some(data) = data {
+                            return TypedArray::from(data).serialize(serializer);
+                        }
+                    })*
+                    _ => {}
+                }
+            };
+        }
+        typed! {
+            U8(u8), U16(u16), U32(u32), U64(u64), I8(i8), I16(i16), I32(i32), I64(i64),
+            F32(f32), F64(f64)
+        }
+        self.0.serialize(serializer)
+    }
+}
+
+/// A number that has a typed array.
+enum Number {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+/// Finds the [`Number`] an element serializes as.
+struct NumberSerializer;
+
+#[derive(Debug)]
+struct NotNumber;
+
+impl fmt::Display for NotNumber {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("not an element of a typed array")
+    }
+}
+
+impl ser::StdError for NotNumber {}
+
+impl ser::Error for NotNumber {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        NotNumber
+    }
+}
+
+macro_rules! number {
+    ($($method:ident($t:ty) => $variant:ident;)*) => {
+        $(fn $method(self, v: $t) -> Result<Number, NotNumber> {
+            Ok(Number::$variant(v))
+        })*
+    };
+}
+
+macro_rules! not_number {
+    ($($method:ident($($arg:ty),*);)*) => {
+        $(fn $method(self, $(_: $arg),*) -> Result<Number, NotNumber> {
+            Err(NotNumber)
+        })*
+    };
+}
+
+impl Serializer for NumberSerializer {
+    type Ok = Number;
+    type Error = NotNumber;
+    type SerializeSeq = ser::Impossible<Number, NotNumber>;
+    type SerializeTuple = ser::Impossible<Number, NotNumber>;
+    type SerializeTupleStruct = ser::Impossible<Number, NotNumber>;
+    type SerializeTupleVariant = ser::Impossible<Number, NotNumber>;
+    type SerializeMap = ser::Impossible<Number, NotNumber>;
+    type SerializeStruct = ser::Impossible<Number, NotNumber>;
+    type SerializeStructVariant = ser::Impossible<Number, NotNumber>;
+
+    number! {
+        serialize_u8(u8) => U8;
+        serialize_u16(u16) => U16;
+        serialize_u32(u32) => U32;
+        serialize_u64(u64) => U64;
+        serialize_i8(i8) => I8;
+        serialize_i16(i16) => I16;
+        serialize_i32(i32) => I32;
+        serialize_i64(i64) => I64;
+        serialize_f32(f32) => F32;
+        serialize_f64(f64) => F64;
+    }
+
+    not_number! {
+        serialize_bool(bool);
+        serialize_char(char);
+        serialize_str(&str);
+        serialize_bytes(&[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(&'static str);
+        serialize_unit_variant(&'static str, u32, &'static str);
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<Number, NotNumber> {
+        Err(NotNumber)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _value: &T,
+    ) -> Result<Number, NotNumber> {
+        Err(NotNumber)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Number, NotNumber> {
+        Err(NotNumber)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, NotNumber> {
+        Err(NotNumber)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, NotNumber> {
+        Err(NotNumber)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, NotNumber> {
+        Err(NotNumber)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, NotNumber> {
+        Err(NotNumber)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, NotNumber> {
+        Err(NotNumber)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, NotNumber> {
+        Err(NotNumber)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, NotNumber> {
+        Err(NotNumber)
+    }
+
+    fn collect_str<T: ?Sized + fmt::Display>(self, _value: &T) -> Result<Number, NotNumber> {
+        Err(NotNumber)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_slice, to_vec};
+    use ndarray::{arr2, Array1, Array2, Array3, ArrayD, ArrayView2};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Matrix(#[serde(with = "super")] Array2<i16>);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Names(#[serde(with = "super")] Array1<String>);
+
+    #[test]
+    fn test_ndarray() {
+        let m = Matrix(arr2(&[[1, 2, 3], [4, 5, 6]]));
+        let data = to_vec(&m).unwrap();
+        // 40([[2, 3], 77(h'...')])
+        assert_eq!(&data[..8], [0xd8, 0x28, 0x82, 0x82, 0x02, 0x03, 0xd8, 0x4d]);
+        assert_eq!(&data[9..13], [1, 0, 2, 0]);
+        assert_eq!(m, from_slice(&data).unwrap());
+
+        // the logical order is kept for other memory layouts
+        let t = Matrix(m.0.t().to_owned());
+        let data = to_vec(&t).unwrap();
+        assert_eq!(&data[9..13], [1, 0, 4, 0]);
+        assert_eq!(t, from_slice(&data).unwrap());
+
+        let n = Names(Array1::from(vec!["a".to_string()]));
+        let data = to_vec(&n).unwrap();
+        assert_eq!(data, [0xd8, 0x28, 0x82, 0x81, 0x01, 0x81, 0x61, 0x61]);
+        assert_eq!(n, from_slice(&data).unwrap());
+    }
+
+    #[test]
+    fn test_view() {
+        #[derive(Serialize)]
+        struct View<'a>(#[serde(serialize_with = "super::serialize")] ArrayView2<'a, i16>);
+        #[derive(Serialize)]
+        struct Borrowed<'a>(#[serde(serialize_with = "super::serialize")] Array1<&'a str>);
+
+        let m = arr2(&[[1, 2, 3], [4, 5, 6]]);
+        let data = to_vec(&View(m.t())).unwrap();
+        // 40([[3, 2], 77(h'...')])
+        assert_eq!(&data[..8], [0xd8, 0x28, 0x82, 0x82, 0x03, 0x02, 0xd8, 0x4d]);
+        assert_eq!(&data[9..13], [1, 0, 4, 0]);
+        let t: Matrix = from_slice(&data).unwrap();
+        assert_eq!(m.t(), t.0);
+
+        let s = String::from("a");
+        let data = to_vec(&Borrowed(Array1::from(vec![s.as_str()]))).unwrap();
+        assert_eq!(data, [0xd8, 0x28, 0x82, 0x81, 0x01, 0x81, 0x61, 0x61]);
+    }
+
+    #[test]
+    fn test_column_major() {
+        // 1040([[2, 3], [1, 4, 2, 5, 3, 6]])
+        let data = [
+            0xd9, 0x04, 0x10, 0x82, 0x82, 0x02, 0x03, 0x86, 1, 4, 2, 5, 3, 6,
+        ];
+        let m: Matrix = from_slice(&data).unwrap();
+        assert_eq!(arr2(&[[1, 2, 3], [4, 5, 6]]), m.0);
+    }
+
+    #[test]
+    fn test_invalid() {
+        #[derive(Debug, Deserialize)]
+        struct Cube(#[serde(with = "super")] Array3<i16>);
+        #[derive(Debug, Deserialize)]
+        struct Dyn(#[serde(with = "super")] ArrayD<i16>);
+
+        let data = to_vec(&Matrix(arr2(&[[1, 2], [3, 4]]))).unwrap();
+        assert!(from_slice::<Cube>(&data).map(|c| c.0.ndim()).is_err());
+        assert_eq!(&[2, 2], from_slice::<Dyn>(&data).unwrap().0.shape());
+        // dims and data disagree
+        assert!(from_slice::<Matrix>(&[0xd8, 0x28, 0x82, 0x82, 0x02, 0x02, 0x81, 0x01]).is_err());
+        // other tags
+        assert!(from_slice::<Matrix>(&[0xd8, 0x29, 0x82, 0x81, 0x01, 0x81, 0x01]).is_err());
+    }
+}
@@ -0,0 +1,638 @@
+//! IP addresses and prefixes, tags 52 and 54 (RFC 9164).
+//!
+//! [`Ip`] writes an address as tag 52 (IPv4) or tag 54 (IPv6) over its octets,
+//! [`IpPrefix`] writes a network as the same tag over `[length, octets]`, with
+//! the trailing zero octets left out.
+//!
+//! When the serializer is not human readable, see
+//! [`Config::human_readable`](crate::Config::human_readable), std's `IpAddr` is
+//! written like `Ip<IpAddr>` without a wrapper. `Ipv4Addr` and `Ipv6Addr` keep
+//! their array of octets, but accept the tagged form when decoding, as does `IpAddr`.
+//!
+//! ```
+//! use minicbor_ser::net::{Ip, IpPrefix};
+//! use minicbor_ser::{from_slice, to_vec};
+//! use std::net::{IpAddr, Ipv4Addr};
+//!
+//! let data = to_vec(&Ip(Ipv4Addr::new(192, 0, 2, 1))).unwrap();
+//! assert_eq!(data, [0xd8, 0x34, 0x44, 0xc0, 0x00, 0x02, 0x01]);
+//!
+//! let net = IpPrefix::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 0)), 24).unwrap();
+//! let data = to_vec(&net).unwrap();
+//! assert_eq!(data, [0xd8, 0x34, 0x82, 0x18, 0x18, 0x43, 0xc0, 0x00, 0x02]);
+//! assert_eq!(net, from_slice(&data).unwrap());
+//! ```
+
+use crate::lib::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::lib::*;
+use crate::tag::Tagged;
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Unexpected, Visitor};
+use serde::ser::{self, Serialize, Serializer};
+
+/// IPv4 address or prefix.
+pub(crate) const IPV4: u64 = 52;
+/// IPv6 address or prefix.
+pub(crate) const IPV6: u64 = 54;
+
+/// The enum name std's `IpAddr` serializes with.
+pub(crate) const IP_ADDR_NAME: &str = "IpAddr";
+/// The variants of std's `IpAddr`.
+pub(crate) const IP_ADDR_VARIANTS: &[&str] = &["V4", "V6"];
+
+/// An IP address, written as tag 52 or 54 over its octets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Ip<A>(pub A);
+
+/// The address types an [`Ip`] can hold: `IpAddr`, `Ipv4Addr` and `Ipv6Addr`.
+pub trait Address: Sized + private::Sealed {
+    #[doc(hidden)]
+    const EXPECTED: &'static str;
+    #[doc(hidden)]
+    fn into_ip(self) -> IpAddr;
+    #[doc(hidden)]
+    fn from_ip(ip: IpAddr) -> Option<Self>;
+}
+
+mod private {
+    pub trait Sealed {}
+    impl Sealed for super::IpAddr {}
+    impl Sealed for super::Ipv4Addr {}
+    impl Sealed for super::Ipv6Addr {}
+}
+
+impl Address for IpAddr {
+    const EXPECTED: &'static str = "an IP address";
+    fn into_ip(self) -> IpAddr {
+        self
+    }
+    fn from_ip(ip: IpAddr) -> Option<Self> {
+        Some(ip)
+    }
+}
+
+impl Address for Ipv4Addr {
+    const EXPECTED: &'static str = "an IPv4 address";
+    fn into_ip(self) -> IpAddr {
+        IpAddr::V4(self)
+    }
+    fn from_ip(ip: IpAddr) -> Option<Self> {
+        match ip {
+            IpAddr::V4(ip) => Some(ip),
+            IpAddr::V6(_) => None,
+        }
+    }
+}
+
+impl Address for Ipv6Addr {
+    const EXPECTED: &'static str = "an IPv6 address";
+    fn into_ip(self) -> IpAddr {
+        IpAddr::V6(self)
+    }
+    fn from_ip(ip: IpAddr) -> Option<Self> {
+        match ip {
+            IpAddr::V4(_) => None,
+            IpAddr::V6(ip) => Some(ip),
+        }
+    }
+}
+
+impl<A: Address + Copy> Serialize for Ip<A> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let octets = Octets::of(self.0.into_ip());
+        Tagged::new(octets.tag(), octets).serialize(serializer)
+    }
+}
+
+impl<'de, A: Address> Deserialize<'de> for Ip<A> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let tagged = Tagged::<Octets>::deserialize(deserializer)?;
+        let octets = tagged.value.as_slice();
+        let ip = match (tagged.tag, octets.len()) {
+            (IPV4, 4) | (IPV6, 16) => tagged.value.to_ip(tagged.tag),
+            (IPV4, _) | (IPV6, _) => {
+                return Err(de::Error::invalid_length(octets.len(), &"4 or 16 octets"))
+            }
+            (tag, _) => {
+                return Err(de::Error::invalid_value(
+                    Unexpected::Unsigned(tag),
+                    &"tag 52 or 54",
+                ))
+            }
+        };
+        A::from_ip(ip)
+            .map(Ip)
+            .ok_or_else(|| de::Error::invalid_value(Unexpected::Bytes(octets), &A::EXPECTED))
+    }
+}
+
+/// An IP network, the address with all bits after the prefix cleared.
+///
+/// Written as tag 52 or 54 over `[length, octets]`, without trailing zero octets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IpPrefix {
+    addr: IpAddr,
+    len: u8,
+}
+
+impl IpPrefix {
+    /// The network of `addr` with a prefix of `len` bits, `None` if `len` is
+    /// longer than the address.
+    pub fn new(addr: IpAddr, len: u8) -> Option<Self> {
+        let mut octets = Octets::of(addr);
+        if u32::from(len) > octets.len as u32 * 8 {
+            return None;
+        }
+        octets.mask(len);
+        Some(IpPrefix {
+            addr: octets.to_ip(octets.tag()),
+            len,
+        })
+    }
+
+    /// The network address.
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// The length of the prefix in bits.
+    pub fn prefix_len(&self) -> u8 {
+        self.len
+    }
+}
+
+impl Serialize for IpPrefix {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut octets = Octets::of(self.addr);
+        let tag = octets.tag();
+        while octets.len > 0 && octets.bytes[octets.len - 1] == 0 {
+            octets.len -= 1;
+        }
+        Tagged::new(tag, (self.len, octets)).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for IpPrefix {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let tagged = Tagged::<(u8, Octets)>::deserialize(deserializer)?;
+        let (len, mut octets) = tagged.value;
+        let width = match tagged.tag {
+            IPV4 => 4,
+            IPV6 => 16,
+            tag => {
+                return Err(de::Error::invalid_value(
+                    Unexpected::Unsigned(tag),
+                    &"tag 52 or 54",
+                ))
+            }
+        };
+        if octets.len > width || u32::from(len) > width as u32 * 8 {
+            return Err(de::Error::invalid_length(
+                octets.len,
+                &"a prefix within the address",
+            ));
+        }
+        // the bits after the prefix must be zero
+        let given = octets;
+        octets.mask(len);
+        if octets != given {
+            return Err(de::Error::invalid_value(
+                Unexpected::Bytes(given.as_slice()),
+                &"no bits set after the prefix",
+            ));
+        }
+        octets.len = width;
+        Ok(IpPrefix {
+            addr: octets.to_ip(tagged.tag),
+            len,
+        })
+    }
+}
+
+/// Up to 16 octets of an address, serialized as a byte string.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Octets {
+    bytes: [u8; 16],
+    len: usize,
+}
+
+impl Octets {
+    fn of(ip: IpAddr) -> Self {
+        let mut bytes = [0; 16];
+        let len = match ip {
+            IpAddr::V4(ip) => {
+                bytes[..4].copy_from_slice(&ip.octets());
+                4
+            }
+            IpAddr::V6(ip) => {
+                bytes = ip.octets();
+                16
+            }
+        };
+        Octets { bytes, len }
+    }
+
+    fn tag(&self) -> u64 {
+        if self.len == 4 {
+            IPV4
+        } else {
+            IPV6
+        }
+    }
+
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    /// The address of the given tag, missing octets are zero.
+    fn to_ip(self, tag: u64) -> IpAddr {
+        let b = self.bytes;
+        if tag == IPV4 {
+            IpAddr::V4(Ipv4Addr::new(b[0], b[1], b[2], b[3]))
+        } else {
+            IpAddr::V6(Ipv6Addr::from(b))
+        }
+    }
+
+    /// Clear the bits after the first `len`.
+    fn mask(&mut self, len: u8) {
+        let len = usize::from(len);
+        for (i, byte) in self.bytes.iter_mut().enumerate() {
+            if i * 8 >= len {
+                *byte = 0;
+            } else if i * 8 + 8 > len {
+                *byte &= 0xff << (8 - (len - i * 8));
+            }
+        }
+    }
+}
+
+impl Serialize for Octets {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.as_slice())
+    }
+}
+
+impl<'de> Deserialize<'de> for Octets {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(OctetsVisitor)
+    }
+}
+
+struct OctetsVisitor;
+
+impl<'de> Visitor<'de> for OctetsVisitor {
+    type Value = Octets;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("at most 16 octets")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if v.len() > 16 {
+            return Err(E::invalid_length(v.len(), &self));
+        }
+        let mut bytes = [0; 16];
+        bytes[..v.len()].copy_from_slice(v);
+        Ok(Octets {
+            bytes,
+            len: v.len(),
+        })
+    }
+
+    /// Octets in formats without byte strings.
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut octets = Octets {
+            bytes: [0; 16],
+            len: 0,
+        };
+        while let Some(b) = seq.next_element()? {
+            if octets.len == 16 {
+                return Err(de::Error::invalid_length(17, &self));
+            }
+            octets.bytes[octets.len] = b;
+            octets.len += 1;
+        }
+        Ok(octets)
+    }
+}
+
+/// The tag and octets of a variant of std's `IpAddr` in its compact form,
+/// `None` if `value` isn't 4 or 16 octets matching the variant.
+///
+/// Other enums may be named `IpAddr` too, these keep the usual enum form.
+pub(crate) fn std_octets<T>(variant_index: u32, variant: &str, value: &T) -> Option<(u64, Octets)>
+where
+    T: ?Sized + Serialize,
+{
+    let (tag, width) = match (variant_index, variant) {
+        (0, "V4") => (IPV4, 4),
+        (1, "V6") => (IPV6, 16),
+        _ => return None,
+    };
+    let mut octets = Octets {
+        bytes: [0; 16],
+        len: 0,
+    };
+    value
+        .serialize(OctetsSerializer {
+            octets: &mut octets,
+            element: false,
+        })
+        .ok()?;
+    if octets.len != width {
+        return None;
+    }
+    Some((tag, octets))
+}
+
+/// Collects a tuple of `u8`, the compact form of `Ipv4Addr` and `Ipv6Addr`.
+struct OctetsSerializer<'a> {
+    octets: &'a mut Octets,
+    /// Whether an element of the tuple is expected.
+    element: bool,
+}
+
+#[derive(Debug)]
+struct NotOctets;
+
+impl fmt::Display for NotOctets {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("not the octets of an IP address")
+    }
+}
+
+impl ser::StdError for NotOctets {}
+
+impl ser::Error for NotOctets {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        NotOctets
+    }
+}
+
+macro_rules! not_octets {
+    ($($method:ident($($arg:ty),*);)*) => {
+        $(fn $method(self, $(_: $arg),*) -> Result<(), NotOctets> {
+            Err(NotOctets)
+        })*
+    };
+}
+
+impl<'a> Serializer for OctetsSerializer<'a> {
+    type Ok = ();
+    type Error = NotOctets;
+    type SerializeSeq = ser::Impossible<(), NotOctets>;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = ser::Impossible<(), NotOctets>;
+    type SerializeTupleVariant = ser::Impossible<(), NotOctets>;
+    type SerializeMap = ser::Impossible<(), NotOctets>;
+    type SerializeStruct = ser::Impossible<(), NotOctets>;
+    type SerializeStructVariant = ser::Impossible<(), NotOctets>;
+
+    fn serialize_u8(self, v: u8) -> Result<(), NotOctets> {
+        if !self.element || self.octets.len == 16 {
+            return Err(NotOctets);
+        }
+        self.octets.bytes[self.octets.len] = v;
+        self.octets.len += 1;
+        Ok(())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, NotOctets> {
+        if self.element {
+            return Err(NotOctets);
+        }
+        Ok(self)
+    }
+
+    not_octets! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+        serialize_bytes(&[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(&'static str);
+        serialize_unit_variant(&'static str, u32, &'static str);
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<(), NotOctets> {
+        Err(NotOctets)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _value: &T,
+    ) -> Result<(), NotOctets> {
+        Err(NotOctets)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), NotOctets> {
+        Err(NotOctets)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, NotOctets> {
+        Err(NotOctets)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, NotOctets> {
+        Err(NotOctets)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, NotOctets> {
+        Err(NotOctets)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, NotOctets> {
+        Err(NotOctets)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, NotOctets> {
+        Err(NotOctets)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, NotOctets> {
+        Err(NotOctets)
+    }
+
+    fn collect_str<T: ?Sized + fmt::Display>(self, _value: &T) -> Result<(), NotOctets> {
+        Err(NotOctets)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl<'a> ser::SerializeTuple for OctetsSerializer<'a> {
+    type Ok = ();
+    type Error = NotOctets;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), NotOctets> {
+        value.serialize(OctetsSerializer {
+            octets: &mut *self.octets,
+            element: true,
+        })
+    }
+
+    fn end(self) -> Result<(), NotOctets> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::de::Deserializer;
+    use crate::{from_slice, to_vec, to_vec_cfg, Config};
+
+    #[test]
+    fn test_ip() {
+        let v6: Ipv6Addr = "2001:db8:1234:deed:beef:cafe:face:feed".parse().unwrap();
+        let data = to_vec(&Ip(v6)).unwrap();
+        assert_eq!(&data[..3], [0xd8, 0x36, 0x50]);
+        assert_eq!(&data[3..], v6.octets());
+        assert_eq!(Ip(v6), from_slice(&data).unwrap());
+        assert_eq!(Ip(IpAddr::V6(v6)), from_slice(&data).unwrap());
+        let err = from_slice::<Ip<Ipv4Addr>>(&data).unwrap_err();
+        assert!(err.to_string().contains("expected an IPv4 address"), "{}", err);
+        // 52(h'c00002')
+        assert!(from_slice::<Ip<IpAddr>>(&[0xd8, 0x34, 0x43, 0xc0, 0x00, 0x02]).is_err());
+    }
+
+    #[test]
+    fn test_prefix() {
+        let v6: Ipv6Addr = "2001:db8:1234::".parse().unwrap();
+        let net = IpPrefix::new(IpAddr::V6(v6), 48).unwrap();
+        let data = to_vec(&net).unwrap();
+        // 54([48, h'20010db81234'])
+        assert_eq!(
+            data,
+            [0xd8, 0x36, 0x82, 0x18, 0x30, 0x46, 0x20, 0x01, 0x0d, 0xb8, 0x12, 0x34]
+        );
+        assert_eq!(net, from_slice(&data).unwrap());
+
+        let net = IpPrefix::new(IpAddr::V4(Ipv4Addr::new(10, 1, 255, 7)), 12).unwrap();
+        assert_eq!(net.addr(), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 0)));
+        assert_eq!(net, from_slice(&to_vec(&net).unwrap()).unwrap());
+        assert_eq!(None, IpPrefix::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 33));
+
+        // 52([8, h'0a01']) has bits set after the prefix
+        assert!(from_slice::<IpPrefix>(&[0xd8, 0x34, 0x82, 0x08, 0x42, 0x0a, 0x01]).is_err());
+        // 52([0, h'']) is the whole address space
+        let any: IpPrefix = from_slice(&[0xd8, 0x34, 0x82, 0x00, 0x40]).unwrap();
+        assert_eq!(any.addr(), IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    }
+
+    #[test]
+    fn test_std_compact() {
+        let cfg = Config::default().human_readable(false);
+        let v4 = Ipv4Addr::new(192, 0, 2, 1);
+        let data = to_vec_cfg(&IpAddr::V4(v4), cfg).unwrap();
+        assert_eq!(data, [0xd8, 0x34, 0x44, 0xc0, 0x00, 0x02, 0x01]);
+
+        let v6 = Ipv6Addr::LOCALHOST;
+        let data = to_vec_cfg(&IpAddr::V6(v6), cfg).unwrap();
+        assert_eq!(&data[..3], [0xd8, 0x36, 0x50]);
+        let mut de = Deserializer::new_with_config(&data, cfg);
+        assert_eq!(IpAddr::V6(v6), IpAddr::deserialize(&mut de).unwrap());
+        let mut de = Deserializer::new_with_config(&data, cfg);
+        assert_eq!(v6, Ipv6Addr::deserialize(&mut de).unwrap());
+
+        // the octets don't leak into the next field
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct S {
+            a: IpAddr,
+            b: u8,
+        }
+        let s = S {
+            a: IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)),
+            b: 9,
+        };
+        let data = to_vec_cfg(&s, cfg).unwrap();
+        let mut de = Deserializer::new_with_config(&data, cfg);
+        assert_eq!(s, S::deserialize(&mut de).unwrap());
+        // [h'01020304', 9]
+        let data = [0x82, 0x44, 0x01, 0x02, 0x03, 0x04, 0x09];
+        let octets = from_slice::<([u8; 4], u8)>(&data).unwrap();
+        assert_eq!(octets, ([1, 2, 3, 4], 9));
+
+        // other enums named `IpAddr` keep their usual form
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        #[serde(rename = "IpAddr")]
+        enum Host {
+            V4(String),
+            Name(String),
+        }
+        for host in [Host::V4("a".into()), Host::Name("b".into())] {
+            let data = to_vec_cfg(&host, cfg).unwrap();
+            assert_eq!(data[0], 0xa1);
+            let mut de = Deserializer::new_with_config(&data, cfg);
+            assert_eq!(host, Host::deserialize(&mut de).unwrap());
+        }
+
+        // the human readable form is unchanged
+        let data = to_vec(&IpAddr::V4(v4)).unwrap();
+        assert_eq!(&data[1..], b"192.0.2.1");
+    }
+}
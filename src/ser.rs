@@ -1,17 +1,52 @@
 #![allow(unused_variables, dead_code)]
-use super::Config;
+use super::{Config, DateRepr, DateTimeRepr, EnumRepr};
+use crate::{datetime, float, net, tag};
+#[cfg(feature = "alloc")]
+use crate::stringref;
+#[cfg(feature = "alloc")]
+use super::KeyOrder;
 pub use crate::error::en::Error;
-use crate::error::en::ErrorKind;
 use crate::lib::*;
 use core::fmt::Display;
+use minicbor::data::{Int, Tag};
 use minicbor::{encode::Write, Encoder};
-use serde::serde_if_integer128;
 use serde::{self, ser};
 
 pub struct Serializer<W> {
     pub(crate) encoder: Encoder<W>,
     depth: u32,
-    flatten_top: bool,
+    cfg: Config,
+    marker: Marker,
+    /// The strings written so far, with [`Config::string_refs`].
+    #[cfg(feature = "alloc")]
+    strings: Option<stringref::Strings>,
+    /// The index of each shared pointee written so far, with [`Config::shared_values`].
+    #[cfg(feature = "std")]
+    shared: Option<BTreeMap<usize, u64>>,
+}
+
+/// The newtype name [`float::half`] serializes with.
+#[cfg(feature = "half")]
+pub(crate) const HALF_NAME: &str = "@@HALF@@";
+
+/// The meaning of the next call, set by a newtype this serializer recognizes.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    None,
+    /// The `(tag, value)` tuple of a [`Tagged`](crate::Tagged) follows.
+    Tagged,
+    /// The next `u64` is a tag number.
+    TagNumber,
+    /// The next `str` is the RFC 3339 text of a date/time.
+    DateTime,
+    /// The next `str` is the RFC 3339 full-date text of a date.
+    Date,
+    /// The next `u8` is a simple value.
+    #[cfg(feature = "alloc")]
+    Simple,
+    /// The next `u16` holds the bits of a half float.
+    #[cfg(feature = "half")]
+    HalfBits,
 }
 
 impl<T> Serializer<T>
@@ -19,17 +54,20 @@ where
     T: Write,
 {
     pub fn new(w: T) -> Self {
-        Serializer {
-            encoder: Encoder::new(w),
-            depth: 0,
-            flatten_top: false,
-        }
+        Self::new_with_config(w, Config::default())
     }
+    /// [`Config::self_describe`] and [`Config::string_refs`] write a tag before the value,
+    /// these only apply with [`to_writer_cfg`] and [`to_vec_cfg`].
     pub fn new_with_config(w: T, cfg: Config) -> Self {
         Serializer {
             encoder: Encoder::new(w),
             depth: 0,
-            flatten_top: cfg.top_flatten,
+            cfg,
+            marker: Marker::None,
+            #[cfg(feature = "alloc")]
+            strings: None,
+            #[cfg(feature = "std")]
+            shared: (cfg.shared_values && cfg.deterministic.is_none()).then(BTreeMap::new),
         }
     }
     pub fn encoder(&mut self) -> &mut Encoder<T> {
@@ -37,6 +75,189 @@ where
     }
 }
 
+impl<T> Serializer<T>
+where
+    T: Write,
+    T::Error: Display + 'static,
+{
+    /// Write `n` as a tag 2/3 bignum, using the shortest big-endian byte string.
+    fn bignum(&mut self, tag: Tag, n: u128) -> Result<(), Error> {
+        let bytes = n.to_be_bytes();
+        let skip = (n.leading_zeros() / 8) as usize;
+        self.encoder.tag(tag)?;
+        self.bytes(&bytes[skip..])
+    }
+
+    /// Write a text string, or a reference to an equal one written before.
+    fn str(&mut self, s: &str) -> Result<(), Error> {
+        if !self.string_ref(true, s.as_bytes())? {
+            self.encoder.str(s)?;
+        }
+        Ok(())
+    }
+
+    /// Write a byte string, or a reference to an equal one written before.
+    fn bytes(&mut self, b: &[u8]) -> Result<(), Error> {
+        if !self.string_ref(false, b)? {
+            self.encoder.bytes(b)?;
+        }
+        Ok(())
+    }
+
+    /// Write a reference (tag 25) if the string was written before, see [`Config::string_refs`].
+    fn string_ref(&mut self, text: bool, s: &[u8]) -> Result<bool, Error> {
+        #[cfg(feature = "alloc")]
+        if let Some(index) = self.strings.as_mut().and_then(|refs| refs.find(text, s)) {
+            self.encoder.tag(Tag::Unassigned(stringref::REF))?.u64(index)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn preferred_float(&self) -> bool {
+        #[cfg(feature = "alloc")]
+        if self.cfg.deterministic.is_some() {
+            return true;
+        }
+        self.cfg.preferred_float
+    }
+
+    /// Write the head of a data item with the given major type, using the shortest form.
+    fn head(&mut self, major: u8, n: u64) -> Result<(), Error> {
+        let major = major << 5;
+        match n {
+            0..=0x17 => self.write_raw(&[major | n as u8]),
+            0x18..=0xff => self.write_raw(&[major | 24, n as u8]),
+            0x100..=0xffff => {
+                self.write_raw(&[major | 25])?;
+                self.write_raw(&(n as u16).to_be_bytes())
+            }
+            0x1_0000..=0xffff_ffff => {
+                self.write_raw(&[major | 26])?;
+                self.write_raw(&(n as u32).to_be_bytes())
+            }
+            _ => {
+                self.write_raw(&[major | 27])?;
+                self.write_raw(&n.to_be_bytes())
+            }
+        }
+    }
+
+    /// Write the bits of a half float.
+    fn f16(&mut self, bits: u16) -> Result<(), Error> {
+        let [hi, lo] = bits.to_be_bytes();
+        self.write_raw(&[0xf9, hi, lo])
+    }
+
+    /// Write the discriminant of an enum variant, see [`Config::variant_index`].
+    fn variant(&mut self, index: u32, name: &'static str) -> Result<(), Error> {
+        if self.cfg.variant_index {
+            self.encoder.u32(index)?;
+        } else {
+            self.str(name)?;
+        }
+        Ok(())
+    }
+
+    /// Start a `[variant, field1, field2, ...]` array, see [`EnumRepr::Array`].
+    fn variant_array(
+        &mut self,
+        index: u32,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Compound<'_, T>, Error> {
+        self.encoder.array(len as u64 + 1)?;
+        self.variant(index, name)?;
+        Ok(Compound::Map {
+            ser: self,
+            state: State::First(Some(len)),
+        })
+    }
+
+    /// Serialize `value` on its own, one level below the current depth.
+    #[cfg(feature = "alloc")]
+    fn to_nested_vec<V>(&self, value: &V) -> Result<Vec<u8>, Error>
+    where
+        V: ?Sized + ser::Serialize,
+    {
+        let mut nested = Serializer::new_with_config(Vec::new(), self.cfg);
+        nested.depth = self.depth + 1;
+        value.serialize(&mut nested)?;
+        Ok(nested.encoder.into_writer())
+    }
+
+    /// Write the pointee of a [`Shared`](crate::shared::Shared) as a shareable value (tag 28),
+    /// or as a reference (tag 29) if it was written before.
+    #[cfg(feature = "std")]
+    fn shared<V>(&mut self, value: &V) -> Result<(), Error>
+    where
+        V: ?Sized + ser::Serialize,
+    {
+        let written = match self.shared.as_mut() {
+            Some(written) => written,
+            None => return value.serialize(self),
+        };
+        let addr = value as *const V as *const () as usize;
+        if let Some(index) = written.get(&addr) {
+            self.encoder
+                .tag(Tag::Unassigned(crate::shared::SHARED_REF))?
+                .u64(*index)?;
+            return Ok(());
+        }
+        written.insert(addr, written.len() as u64);
+        self.encoder.tag(Tag::Unassigned(crate::shared::SHAREABLE))?;
+        value.serialize(self)
+    }
+
+    /// Copy already encoded CBOR to the output.
+    fn write_raw(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.encoder
+            .writer_mut()
+            .write_all(bytes)
+            .map_err(minicbor::encode::Error::write)?;
+        Ok(())
+    }
+
+    /// Write a date/time given as RFC 3339 text as tag 0.
+    fn date_time(&mut self, text: &str) -> Result<(), Error> {
+        self.encoder.tag(Tag::DateTime)?;
+        self.str(text)
+    }
+
+    /// Write a date/time given as nanoseconds since the epoch as tag 1, an integer
+    /// if it's whole seconds.
+    fn timestamp(&mut self, nanos: i128) -> Result<(), Error> {
+        let per_sec = 1_000_000_000;
+        let ts = i64::try_from(nanos.div_euclid(per_sec))
+            .map(|secs| datetime::Timestamp {
+                secs,
+                nanos: nanos.rem_euclid(per_sec) as u32,
+                offset: 0,
+            })
+            .map_err(|_| <Error as ser::Error>::custom("date/time out of range"))?;
+        self.encoder.tag(Tag::Timestamp)?;
+        if ts.nanos == 0 {
+            self.encoder.i64(ts.secs)?;
+        } else {
+            ser::Serializer::serialize_f64(self, ts.epoch_f64())?;
+        }
+        Ok(())
+    }
+
+    /// Write a date given as RFC 3339 full-date text as tag 1004.
+    fn date(&mut self, text: &str) -> Result<(), Error> {
+        self.encoder.tag(Tag::Unassigned(datetime::FULL_DATE))?;
+        self.str(text)
+    }
+
+    /// Write a date given as days since the epoch as tag 100.
+    fn days(&mut self, days: i64) -> Result<(), Error> {
+        self.encoder.tag(Tag::Unassigned(datetime::DAYS))?;
+        self.encoder.i64(days)?;
+        Ok(())
+    }
+}
+
 impl<'a, W> ser::Serializer for &'a mut Serializer<W>
 where
     W: Write,
@@ -78,18 +299,37 @@ where
 
     #[inline]
     fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        if self.marker == Marker::Date {
+            self.marker = Marker::None;
+            return self.days(v);
+        }
         self.encoder.i64(v)?;
         Ok(())
     }
 
     #[inline]
     fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        #[cfg(feature = "alloc")]
+        if self.marker == Marker::Simple {
+            self.marker = Marker::None;
+            // 20 to 23 have types of their own, 24 to 31 are not well-formed
+            if (20..32).contains(&v) {
+                return Err(<Error as ser::Error>::custom("reserved simple value"));
+            }
+            self.encoder.simple(v)?;
+            return Ok(());
+        }
         self.encoder.u8(v)?;
         Ok(())
     }
 
     #[inline]
     fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        #[cfg(feature = "half")]
+        if self.marker == Marker::HalfBits {
+            self.marker = Marker::None;
+            return self.f16(v);
+        }
         self.encoder.u16(v)?;
         Ok(())
     }
@@ -102,18 +342,33 @@ where
 
     #[inline]
     fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        if self.marker == Marker::TagNumber {
+            self.marker = Marker::None;
+            self.encoder.tag(Tag::Unassigned(v))?;
+            return Ok(());
+        }
         self.encoder.u64(v)?;
         Ok(())
     }
 
     #[inline]
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        if self.preferred_float() {
+            if let Some(bits) = float::f32_to_f16(v) {
+                return self.f16(bits);
+            }
+        }
         self.encoder.f32(v)?;
         Ok(())
     }
 
     #[inline]
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        if self.preferred_float() {
+            if let Some(v) = float::f64_to_f32(v) {
+                return self.serialize_f32(v);
+            }
+        }
         self.encoder.f64(v)?;
         Ok(())
     }
@@ -126,14 +381,20 @@ where
 
     #[inline]
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        self.encoder.str(v)?;
-        Ok(())
+        if self.marker == Marker::DateTime {
+            self.marker = Marker::None;
+            return self.date_time(v);
+        }
+        if self.marker == Marker::Date {
+            self.marker = Marker::None;
+            return self.date(v);
+        }
+        self.str(v)
     }
 
     #[inline]
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
-        self.encoder.bytes(v)?;
-        Ok(())
+        self.bytes(v)
     }
 
     #[inline]
@@ -142,6 +403,7 @@ where
     }
 
     #[inline]
+    #[allow(clippy::multiple_bound_locations)]
     fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error>
     where
         T: ser::Serialize,
@@ -157,6 +419,11 @@ where
 
     #[inline]
     fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        #[cfg(feature = "alloc")]
+        if name == crate::value::UNDEFINED_NAME {
+            self.encoder.undefined()?;
+            return Ok(());
+        }
         self.serialize_unit()
     }
 
@@ -167,10 +434,14 @@ where
         variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        self.serialize_str(variant)
+        if self.cfg.enum_repr == EnumRepr::Array {
+            self.encoder.array(1)?;
+        }
+        self.variant(variant_index, variant)
     }
 
     #[inline]
+    #[allow(clippy::multiple_bound_locations)]
     fn serialize_newtype_struct<T: ?Sized>(
         self,
         name: &'static str,
@@ -179,10 +450,27 @@ where
     where
         T: ser::Serialize,
     {
-        value.serialize(self)
+        let marker = match name {
+            tag::TAGGED_NAME => Marker::Tagged,
+            datetime::DATETIME_NAME => Marker::DateTime,
+            datetime::DATE_NAME => Marker::Date,
+            #[cfg(feature = "alloc")]
+            crate::value::SIMPLE_NAME => Marker::Simple,
+            #[cfg(feature = "std")]
+            crate::shared::SHARED_NAME => return self.shared(value),
+            // `float::half` serializes a newtype of the bits.
+            #[cfg(feature = "half")]
+            HALF_NAME => Marker::HalfBits,
+            _ => return value.serialize(self),
+        };
+        self.marker = marker;
+        let result = value.serialize(&mut *self);
+        self.marker = Marker::None;
+        result
     }
 
     #[inline]
+    #[allow(clippy::multiple_bound_locations)]
     fn serialize_newtype_variant<T: ?Sized>(
         self,
         name: &'static str,
@@ -193,19 +481,47 @@ where
     where
         T: ser::Serialize,
     {
-        self.encoder.map(1)?.str(variant)?;
+        // the compact form of std's `IpAddr` becomes tag 52 or 54 over the octets
+        if name == net::IP_ADDR_NAME && self.cfg.compact {
+            if let Some((tag, octets)) = net::std_octets(variant_index, variant, value) {
+                self.encoder.tag(Tag::Unassigned(tag))?;
+                return self.bytes(octets.as_slice());
+            }
+        }
+        // the variants of a tag-dispatched enum are named after their tag number
+        if name == tag::TAGGED_NAME {
+            let tag = variant.parse().map_err(|_| {
+                <Error as ser::Error>::custom("expected a tag number as variant name")
+            })?;
+            self.encoder.tag(Tag::Unassigned(tag))?;
+            return value.serialize(&mut *self);
+        }
+        match self.cfg.enum_repr {
+            EnumRepr::Map => self.encoder.map(1)?,
+            EnumRepr::Array => self.encoder.array(2)?,
+        };
+        self.variant(variant_index, variant)?;
         value.serialize(&mut *self)?;
         Ok(())
     }
 
     #[inline]
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
-        if self.depth == 0 && self.flatten_top {
+        if self.depth == 0 && self.cfg.top_flatten {
             return Ok(Compound::Map {
                 ser: self,
                 state: State::FlattenFirst,
             });
         }
+        // Deterministic encoding forbids indefinite lengths, so count the elements first.
+        #[cfg(feature = "alloc")]
+        if len.is_none() && self.cfg.deterministic.is_some() {
+            return Ok(Compound::Buffered {
+                ser: self,
+                buf: Vec::new(),
+                len: 0,
+            });
+        }
         match len {
             Some(le) => {
                 if le == 0 {
@@ -234,6 +550,13 @@ where
 
     #[inline]
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        if self.marker == Marker::Tagged {
+            self.marker = Marker::None;
+            return Ok(Compound::Map {
+                ser: self,
+                state: State::Tag,
+            });
+        }
         self.serialize_seq(Some(len))
     }
 
@@ -254,13 +577,27 @@ where
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        self.encoder.map(1)?.str(variant)?;
+        if self.cfg.enum_repr == EnumRepr::Array {
+            return self.variant_array(variant_index, variant, len);
+        }
+        self.encoder.map(1)?;
+        self.variant(variant_index, variant)?;
         self.serialize_seq(Some(len))
     }
 
     #[inline]
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
-        if self.flatten_top && self.depth == 0 {
+        #[cfg(feature = "alloc")]
+        if let Some(order) = self.cfg.deterministic {
+            let flatten = self.cfg.top_flatten && self.depth == 0;
+            return Ok(Compound::Sorted {
+                ser: self,
+                order,
+                entries: Vec::new(),
+                flatten,
+            });
+        }
+        if self.cfg.top_flatten && self.depth == 0 {
             return Ok(Compound::Map {
                 ser: self,
                 state: State::FlattenFirst,
@@ -298,6 +635,9 @@ where
         name: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
+        if self.cfg.packed {
+            return self.serialize_seq(Some(len));
+        }
         self.serialize_map(Some(len))
     }
 
@@ -309,41 +649,162 @@ where
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        if self.flatten_top && self.depth == 0 {
+        if self.cfg.top_flatten && self.depth == 0 {
             return Ok(Compound::Map {
                 ser: self,
                 state: State::FlattenFirst,
             });
         }
-        self.encoder.map(1)?.str(variant)?;
-        Ok(Compound::Map {
-            ser: self,
-            state: State::First(Some(len)),
-        })
+        if self.cfg.enum_repr == EnumRepr::Array {
+            return self.variant_array(variant_index, variant, len);
+        }
+        self.encoder.map(1)?;
+        self.variant(variant_index, variant)?;
+        self.serialize_struct(name, len)
+    }
+
+    #[inline]
+    fn is_human_readable(&self) -> bool {
+        // a flipped answer asks a timestamp or date for its number, see `datetime::Text`
+        let readable = !self.cfg.compact;
+        let number = match self.marker {
+            Marker::DateTime => self.cfg.date_time == DateTimeRepr::Epoch,
+            Marker::Date => self.cfg.date == DateRepr::Days,
+            _ => false,
+        };
+        if number {
+            !readable
+        } else {
+            readable
+        }
     }
 
     #[inline]
+    #[allow(clippy::multiple_bound_locations)]
     fn collect_str<T: ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error>
     where
         T: Display,
     {
-        // Temporary:
+        // Deterministic encoding needs a definite length, so format twice.
         #[cfg(feature = "alloc")]
-        { self.serialize_str(&value.to_string()) }
-        #[cfg(not(feature = "alloc"))]
-        { unreachable!() }
+        if self.cfg.deterministic.is_some() {
+            let mut len = StrLen(0);
+            fmt::Write::write_fmt(&mut len, format_args!("{}", value))
+                .map_err(|_| <Error as ser::Error>::custom("formatting error"))?;
+            self.head(MAJOR_TEXT, len.0 as u64)?;
+            let written = StrChunks::write(self, value, true)?;
+            if written != len.0 {
+                return Err(ser::Error::custom("`Display` output changed between calls"));
+            }
+            return Ok(());
+        }
+        self.encoder.begin_str()?;
+        StrChunks::write(self, value, false)?;
+        self.encoder.end()?;
+        Ok(())
     }
 
-    serde_if_integer128! {
-        #[inline]
-        fn serialize_i128(self, v:i128) ->Result<Self::Ok,Self::Error> {
-            Err(super::error::en::make_kind_err(ErrorKind::Unsupported128BitInteger, "128-bit integers are not currently supported."))
+    #[inline]
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        if self.marker == Marker::DateTime {
+            self.marker = Marker::None;
+            return self.timestamp(v);
+        }
+        if let Ok(n) = Int::try_from(v) {
+            self.encoder.int(n)?;
+            return Ok(());
+        }
+        // A negative bignum holds `-1 - v`, just like a CBOR negative integer.
+        if v < 0 {
+            self.bignum(Tag::NegBignum, (-1 - v) as u128)
+        } else {
+            self.bignum(Tag::PosBignum, v as u128)
         }
     }
-    serde_if_integer128! {
-        #[inline]
-        fn serialize_u128(self, v:u128) ->Result<Self::Ok,Self::Error> {
-            Err(super::error::en::make_kind_err(ErrorKind::Unsupported128BitInteger, "128-bit integers are not currently supported."))
+
+    #[inline]
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        if let Ok(n) = u64::try_from(v) {
+            self.encoder.u64(n)?;
+            return Ok(());
+        }
+        self.bignum(Tag::PosBignum, v)
+    }
+}
+
+#[cfg(feature = "alloc")]
+const MAJOR_TEXT: u8 = 3;
+
+/// Counts the bytes of `Display` output.
+#[cfg(feature = "alloc")]
+struct StrLen(usize);
+
+#[cfg(feature = "alloc")]
+impl fmt::Write for StrLen {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// Streams `Display` output to the encoder without allocating.
+///
+/// Each piece becomes a chunk of an indefinite length text string,
+/// or is copied as is after a definite length head.
+struct StrChunks<'a, W> {
+    ser: &'a mut Serializer<W>,
+    definite: bool,
+    written: usize,
+    error: Option<Error>,
+}
+
+impl<'a, W> StrChunks<'a, W>
+where
+    W: Write,
+    W::Error: Display + 'static,
+{
+    fn write<T>(ser: &'a mut Serializer<W>, value: &T, definite: bool) -> Result<usize, Error>
+    where
+        T: ?Sized + Display,
+    {
+        let mut chunks = StrChunks {
+            ser,
+            definite,
+            written: 0,
+            error: None,
+        };
+        match fmt::Write::write_fmt(&mut chunks, format_args!("{}", value)) {
+            Ok(()) => Ok(chunks.written),
+            Err(_) => Err(chunks
+                .error
+                .unwrap_or_else(|| ser::Error::custom("formatting error"))),
+        }
+    }
+}
+
+impl<'a, W> fmt::Write for StrChunks<'a, W>
+where
+    W: Write,
+    W::Error: Display + 'static,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.is_empty() {
+            return Ok(());
+        }
+        let result = if self.definite {
+            self.ser.write_raw(s.as_bytes())
+        } else {
+            self.ser.encoder.str(s).map(|_| ()).map_err(Error::from)
+        };
+        match result {
+            Ok(()) => {
+                self.written += s.len();
+                Ok(())
+            }
+            Err(e) => {
+                self.error = Some(e);
+                Err(fmt::Error)
+            }
         }
     }
 }
@@ -352,6 +813,8 @@ where
 #[derive(PartialEq, Eq)]
 /// Not public API.
 pub enum State {
+    /// The tag number of a tagged value, followed by the value itself.
+    Tag,
     First(Option<usize>),
     Empty,
     Rest(Option<usize>),
@@ -366,6 +829,31 @@ pub enum Compound<'a, W: 'a> {
         ser: &'a mut Serializer<W>,
         state: State,
     },
+    /// Elements of a sequence with unknown length, encoded ahead of the header.
+    #[cfg(feature = "alloc")]
+    Buffered {
+        ser: &'a mut Serializer<W>,
+        buf: Vec<u8>,
+        len: u64,
+    },
+    /// Encoded map entries waiting to be sorted.
+    #[cfg(feature = "alloc")]
+    Sorted {
+        ser: &'a mut Serializer<W>,
+        order: KeyOrder,
+        entries: Vec<(Vec<u8>, Vec<u8>)>,
+        flatten: bool,
+    },
+}
+
+impl<'a, W> Compound<'a, W> {
+    fn cfg(&self) -> Config {
+        match self {
+            Compound::Map { ser, .. } => ser.cfg,
+            #[cfg(feature = "alloc")]
+            Compound::Buffered { ser, .. } | Compound::Sorted { ser, .. } => ser.cfg,
+        }
+    }
 }
 
 impl<'a, W> ser::SerializeSeq for Compound<'a, W>
@@ -376,6 +864,7 @@ where
     type Ok = ();
     type Error = Error;
 
+    #[allow(clippy::multiple_bound_locations)]
     fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ser::Serialize,
@@ -386,6 +875,10 @@ where
                 ref mut state,
             } => {
                 match *state {
+                    State::Tag => {
+                        ser.marker = Marker::TagNumber;
+                        *state = State::Empty;
+                    }
                     State::First(size) => {
                         ser.depth += 1;
                         *state = State::Rest(size);
@@ -399,6 +892,18 @@ where
                 value.serialize(&mut **ser)?;
                 Ok(())
             }
+            #[cfg(feature = "alloc")]
+            Compound::Buffered {
+                ref mut ser,
+                ref mut buf,
+                ref mut len,
+            } => {
+                buf.extend_from_slice(&ser.to_nested_vec(value)?);
+                *len += 1;
+                Ok(())
+            }
+            #[cfg(feature = "alloc")]
+            Compound::Sorted { .. } => unreachable!("sequence element in a map"),
         }
     }
 
@@ -419,6 +924,13 @@ where
                 }
                 Ok(())
             }
+            #[cfg(feature = "alloc")]
+            Compound::Buffered { ser, buf, len } => {
+                ser.encoder.array(len)?;
+                ser.write_raw(&buf)
+            }
+            #[cfg(feature = "alloc")]
+            Compound::Sorted { .. } => unreachable!("sequence end in a map"),
         }
     }
 }
@@ -431,6 +943,7 @@ where
     type Ok = ();
     type Error = Error;
 
+    #[allow(clippy::multiple_bound_locations)]
     fn serialize_field<T: ?Sized>(
         &mut self,
         key: &'static str,
@@ -439,15 +952,17 @@ where
     where
         T: ser::Serialize,
     {
-        match *self {
-            Compound::Map { .. } => ser::SerializeMap::serialize_entry(self, key, value),
+        if self.cfg().packed {
+            return ser::SerializeSeq::serialize_element(self, value);
         }
+        ser::SerializeMap::serialize_entry(self, key, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        match self {
-            Compound::Map { .. } => ser::SerializeMap::end(self),
+        if self.cfg().packed {
+            return ser::SerializeSeq::end(self);
         }
+        ser::SerializeMap::end(self)
     }
 }
 
@@ -459,6 +974,7 @@ where
     type Ok = ();
     type Error = Error;
 
+    #[allow(clippy::multiple_bound_locations)]
     fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ser::Serialize,
@@ -479,6 +995,7 @@ where
     type Ok = ();
     type Error = Error;
 
+    #[allow(clippy::multiple_bound_locations)]
     fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ser::Serialize,
@@ -499,6 +1016,7 @@ where
     type Ok = ();
     type Error = Error;
 
+    #[allow(clippy::multiple_bound_locations)]
     fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ser::Serialize,
@@ -519,6 +1037,7 @@ where
     type Ok = ();
     type Error = Error;
 
+    #[allow(clippy::multiple_bound_locations)]
     fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<(), Self::Error>
     where
         T: ser::Serialize,
@@ -544,9 +1063,21 @@ where
                 key.serialize(&mut **ser)?;
                 Ok(())
             }
+            #[cfg(feature = "alloc")]
+            Compound::Sorted {
+                ref mut ser,
+                ref mut entries,
+                ..
+            } => {
+                entries.push((ser.to_nested_vec(key)?, Vec::new()));
+                Ok(())
+            }
+            #[cfg(feature = "alloc")]
+            Compound::Buffered { .. } => unreachable!("map key in a sequence"),
         }
     }
 
+    #[allow(clippy::multiple_bound_locations)]
     fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ser::Serialize,
@@ -559,6 +1090,20 @@ where
                 value.serialize(&mut **ser)?;
                 Ok(())
             }
+            #[cfg(feature = "alloc")]
+            Compound::Sorted {
+                ref mut ser,
+                ref mut entries,
+                ..
+            } => {
+                let encoded = ser.to_nested_vec(value)?;
+                if let Some(entry) = entries.last_mut() {
+                    entry.1 = encoded;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "alloc")]
+            Compound::Buffered { .. } => unreachable!("map value in a sequence"),
         }
     }
 
@@ -579,6 +1124,25 @@ where
                 }
                 Ok(())
             }
+            #[cfg(feature = "alloc")]
+            Compound::Sorted {
+                ser,
+                order,
+                mut entries,
+                flatten,
+            } => {
+                entries.sort_by(|a, b| order.compare(&a.0, &b.0));
+                if !flatten {
+                    ser.encoder.map(entries.len() as u64)?;
+                }
+                for (key, value) in entries {
+                    ser.write_raw(&key)?;
+                    ser.write_raw(&value)?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "alloc")]
+            Compound::Buffered { .. } => unreachable!("map end in a sequence"),
         }
     }
 }
@@ -591,6 +1155,7 @@ where
     type Ok = ();
     type Error = Error;
 
+    #[allow(clippy::multiple_bound_locations)]
     fn serialize_field<T: ?Sized>(
         &mut self,
         key: &'static str,
@@ -599,12 +1164,16 @@ where
     where
         T: ser::Serialize,
     {
-        match *self {
-            Compound::Map { .. } => ser::SerializeStruct::serialize_field(self, key, value),
+        if self.cfg().enum_repr == EnumRepr::Array {
+            return ser::SerializeSeq::serialize_element(self, value);
         }
+        ser::SerializeStruct::serialize_field(self, key, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
+        if self.cfg().enum_repr == EnumRepr::Array {
+            return ser::SerializeSeq::end(self);
+        }
         ser::SerializeStruct::end(self)
     }
 }
@@ -631,11 +1200,21 @@ where
 /// This function will serialize top-level `struct` and `tuple` in order.
 /// So you should make sure their fields are in the same order.
 pub fn to_vec_flat<T>(value: &T) -> Result<Vec<u8>, Error>
+where
+    T: ?Sized + ser::Serialize,
+{
+    to_vec_cfg(value, Config::default().top_flatten(true))
+}
+
+#[cfg(feature = "alloc")]
+#[inline]
+/// Serialize a CBOR to Vec with the given [`Config`].
+pub fn to_vec_cfg<T>(value: &T, cfg: Config) -> Result<Vec<u8>, Error>
 where
     T: ?Sized + ser::Serialize,
 {
     let mut out = Vec::with_capacity(128);
-    to_writer_cfg(value, &mut out, Config { top_flatten: true })?;
+    to_writer_cfg(value, &mut out, cfg)?;
     Ok(out)
 }
 
@@ -647,6 +1226,14 @@ where
     T: ?Sized + ser::Serialize,
 {
     let mut se = Serializer::new_with_config(writer, cfg);
+    if cfg.self_describe {
+        se.encoder.tag(Tag::Unassigned(tag::SELF_DESCRIBE))?;
+    }
+    #[cfg(feature = "alloc")]
+    if cfg.string_refs && cfg.deterministic.is_none() {
+        se.encoder.tag(Tag::Unassigned(stringref::NAMESPACE))?;
+        se.strings = Some(stringref::Strings::default());
+    }
     value.serialize(&mut se)?;
     Ok(())
 }
@@ -680,11 +1267,13 @@ mod ser_tests {
     use serde::Serialize;
 
     use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
 
     macro_rules! assert_result {
         ($expect:expr, $data:expr , $flt:expr) => {{
             let mut out = Vec::with_capacity(128);
-            to_writer_cfg(&$data, &mut out, Config { top_flatten: $flt }).unwrap();
+            to_writer_cfg(&$data, &mut out, Config::default().top_flatten($flt)).unwrap();
             let __s: Vec<u8> = out;
             let __s = __s.as_slice();
             assert_eq!(
@@ -741,6 +1330,7 @@ mod ser_tests {
     }
 
     #[test]
+    #[allow(clippy::zero_prefixed_literal)]
     fn test_struct() {
         let expect = [
             0xA1u8, 0x65, 0x68, 0x65, 0x6C, 0x6C, 0x6F, 0x65, 0x77, 0x6F, 0x72, 0x6C, 0x64,
@@ -799,4 +1389,180 @@ mod ser_tests {
         );
         assert_result!([0xa1, 0x61, 0x44, 0x84, 0x01, 0x02, 0x03, 0x04], d);
     }
+
+    #[test]
+    fn test_128bit() {
+        assert_result!([0x18, 0xff], 0xffu128);
+        assert_result!([0x38, 0xff], -0x100i128);
+        // -2^64 is still a plain negative integer
+        assert_result!(
+            [0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            -0x1_0000_0000_0000_0000i128
+        );
+        assert_result!(
+            [0xc2, 0x49, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+            0x1_0000_0000_0000_0000u128
+        );
+        assert_result!(
+            [0xc3, 0x49, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+            -0x1_0000_0000_0000_0001i128
+        );
+        let mut max = vec![0xc2u8, 0x50];
+        max.extend_from_slice(&[0xff; 16]);
+        assert_result!(max.as_slice(), u128::MAX);
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let mut map = BTreeMap::new();
+        map.insert(100, 0);
+        map.insert(-1, 0);
+        map.insert(10, 0);
+        let value = to_vec_cfg(&map, Config::default().deterministic(KeyOrder::Lexicographic));
+        assert_eq!([0xa3, 0x0a, 0x00, 0x18, 0x64, 0x00, 0x20, 0x00], value.unwrap().as_slice());
+        let value = to_vec_cfg(&map, Config::default().deterministic(KeyOrder::LengthFirst));
+        assert_eq!([0xa3, 0x0a, 0x00, 0x20, 0x00, 0x18, 0x64, 0x00], value.unwrap().as_slice());
+
+        #[derive(Debug, Serialize)]
+        struct Unordered {
+            bb: u8,
+            a: u8,
+        }
+        let value = to_vec_cfg(
+            &Unordered { bb: 1, a: 2 },
+            Config::default().deterministic(KeyOrder::Lexicographic),
+        );
+        assert_eq!([0xa2, 0x61, 0x61, 0x02, 0x62, 0x62, 0x62, 0x01], value.unwrap().as_slice());
+
+        struct Unsized;
+        impl Serialize for Unsized {
+            fn serialize<S: ser::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                s.collect_seq((1u8..4).filter(|_| true))
+            }
+        }
+        assert_eq!([0x9f, 0x01, 0x02, 0x03, 0xff], to_vec(&Unsized).unwrap().as_slice());
+        let value = to_vec_cfg(&Unsized, Config::default().deterministic(KeyOrder::Lexicographic));
+        assert_eq!([0x83, 0x01, 0x02, 0x03], value.unwrap().as_slice());
+    }
+
+    #[test]
+    fn test_packed() {
+        let packed = Config::default().packed(true);
+        let test_struct = TestStruct2 {
+            a: [1, 2],
+            b: TestStruct {
+                hello: "world".to_string(),
+            },
+        };
+        let expect = [
+            0x82, 0x82, 0x01, 0x02, 0x81, 0x65, 0x77, 0x6F, 0x72, 0x6C, 0x64,
+        ];
+        assert_eq!(expect, to_vec_cfg(&test_struct, packed).unwrap().as_slice());
+
+        #[derive(Debug, Serialize)]
+        enum Variant {
+            S { a: u8, b: u8 },
+        }
+        let value = to_vec_cfg(&Variant::S { a: 1, b: 2 }, packed).unwrap();
+        assert_eq!([0xa1, 0x61, 0x53, 0x82, 0x01, 0x02], value.as_slice());
+    }
+
+    #[test]
+    fn test_variant_index() {
+        let index = Config::default().variant_index(true);
+        let value = to_vec_cfg(&TestEnum::A, index).unwrap();
+        assert_eq!([0x00], value.as_slice());
+        let value = to_vec_cfg(&TestEnum::B(1), index).unwrap();
+        assert_eq!([0xa1, 0x01, 0x01], value.as_slice());
+        let value = to_vec_cfg(&TestEnum::D(&[1]), index).unwrap();
+        assert_eq!([0xa1, 0x03, 0x81, 0x01], value.as_slice());
+    }
+
+    #[test]
+    fn test_enum_array() {
+        let array = Config::default().enum_repr(EnumRepr::Array);
+        let value = to_vec_cfg(&TestEnum::A, array).unwrap();
+        assert_eq!([0x81, 0x61, 0x41], value.as_slice());
+        let value = to_vec_cfg(&TestEnum::B(1), array.variant_index(true)).unwrap();
+        assert_eq!([0x82, 0x01, 0x01], value.as_slice());
+
+        #[derive(Debug, Serialize)]
+        enum Fields {
+            T(u8, u8),
+            S { a: u8, b: u8 },
+        }
+        let value = to_vec_cfg(&Fields::T(1, 2), array).unwrap();
+        assert_eq!([0x83, 0x61, 0x54, 0x01, 0x02], value.as_slice());
+        let value = to_vec_cfg(&Fields::S { a: 1, b: 2 }, array).unwrap();
+        assert_eq!([0x83, 0x61, 0x53, 0x01, 0x02], value.as_slice());
+    }
+
+    #[test]
+    fn test_preferred_float() {
+        let preferred = Config::default().preferred_float(true);
+        assert_result!([0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0], 1.5f64);
+        assert_eq!([0xf9, 0x3e, 0x00], to_vec_cfg(&1.5f64, preferred).unwrap().as_slice());
+        assert_eq!([0xf9, 0x7c, 0x00], to_vec_cfg(&f64::INFINITY, preferred).unwrap().as_slice());
+        assert_eq!([0xf9, 0x7e, 0x00], to_vec_cfg(&f64::NAN, preferred).unwrap().as_slice());
+        assert_eq!(
+            [0xfa, 0x47, 0xc3, 0x50, 0x00],
+            to_vec_cfg(&100000.0f64, preferred).unwrap().as_slice()
+        );
+        assert_eq!(
+            [0xfb, 0x3f, 0xb9, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a],
+            to_vec_cfg(&0.1f64, preferred).unwrap().as_slice()
+        );
+        let deterministic = Config::default().deterministic(KeyOrder::LengthFirst);
+        assert_eq!([0xf9, 0x80, 0x00], to_vec_cfg(&-0.0f32, deterministic).unwrap().as_slice());
+    }
+
+    #[cfg(feature = "half")]
+    #[test]
+    fn test_half() {
+        #[derive(Serialize)]
+        struct Half(#[serde(with = "crate::float::half")] half::f16);
+        assert_result!([0xf9, 0x3e, 0x00], Half(half::f16::from_f32(1.5)));
+        assert_result!([0x19, 0x01, 0x00], 0x100u16);
+
+        // only `float::half` writes a half float, not any newtype named `f16`
+        #[allow(non_camel_case_types)]
+        #[derive(Serialize)]
+        struct f16(u16);
+        assert_result!([0x19, 0x3e, 0x00], f16(0x3e00));
+        assert_result!([0x19, 0x3e, 0x00], half::f16::from_f32(1.5));
+    }
+
+    #[test]
+    fn test_collect_str() {
+        struct Pieces;
+        impl Display for Pieces {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("ab")?;
+                f.write_str("-")?;
+                write!(f, "{}", 1)
+            }
+        }
+        struct Collect<T>(T);
+        impl<T: Display> Serialize for Collect<T> {
+            fn serialize<S: ser::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                s.collect_str(&self.0)
+            }
+        }
+        assert_result!(
+            [0x7f, 0x62, 0x61, 0x62, 0x61, 0x2d, 0x61, 0x31, 0xff],
+            Collect(Pieces)
+        );
+        assert_result!([0x7f, 0xff], Collect(""));
+        let value = to_vec_cfg(
+            &Collect(Pieces),
+            Config::default().deterministic(KeyOrder::Lexicographic),
+        );
+        assert_eq!([0x64, 0x61, 0x62, 0x2d, 0x31], value.unwrap().as_slice());
+    }
+
+    #[test]
+    fn test_self_describe() {
+        let value = to_vec_cfg(&1u8, Config::default().self_describe(true)).unwrap();
+        assert_eq!([0xd9, 0xd9, 0xf7, 0x01], value.as_slice());
+    }
 }
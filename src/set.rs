@@ -0,0 +1,105 @@
+//! Sets, tag 258.
+//!
+//! Sets serialize like any other sequence. [`Set`] writes tag 258 before
+//! the array, so that peers can tell a set from a list.
+//!
+//! Tag 258 is accepted wherever a sequence is expected when decoding. Sets
+//! with duplicate elements can be rejected with
+//! [`Config::unique_sets`](crate::Config::unique_sets).
+//!
+//! ```
+//! use minicbor_ser::set::Set;
+//! use minicbor_ser::{from_slice, to_vec};
+//! use std::collections::BTreeSet;
+//!
+//! let set: BTreeSet<u8> = vec![1, 2].into_iter().collect();
+//! let data = to_vec(&Set(&set)).unwrap();
+//! assert_eq!(data, [0xd9, 0x01, 0x02, 0x82, 0x01, 0x02]);
+//! assert_eq!(set, from_slice::<BTreeSet<u8>>(&data).unwrap());
+//! ```
+
+use crate::lib::*;
+use crate::tag::Tag;
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+/// Mathematical finite set.
+pub(crate) const SET: u64 = 258;
+
+/// A set-like collection, written as tag 258 over an array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Set<T>(pub T);
+
+impl<T: Serialize> Serialize for Set<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Tag::<SET, _>(&self.0).serialize(serializer)
+    }
+}
+
+/// Reads the collection, tagged or not.
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Set<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(Set)
+    }
+}
+
+#[cfg(feature = "std")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::de::Deserializer;
+    use crate::error::de::ErrorKind;
+    use crate::{from_slice, to_vec, Config};
+    use std::collections::{BTreeSet, HashSet};
+
+    #[test]
+    fn test_set() {
+        let set: HashSet<String> = vec!["a".to_string()].into_iter().collect();
+        let data = to_vec(&Set(&set)).unwrap();
+        assert_eq!(data, [0xd9, 0x01, 0x02, 0x81, 0x61, 0x61]);
+        assert_eq!(Set(set), from_slice(&data).unwrap());
+
+        // wherever a sequence is expected
+        let v: Vec<u8> = from_slice(&[0xd9, 0x01, 0x02, 0x82, 0x01, 0x02]).unwrap();
+        assert_eq!(vec![1, 2], v);
+        let v: (u8, u8) = from_slice(&[0xd9, 0x01, 0x02, 0x82, 0x01, 0x02]).unwrap();
+        assert_eq!((1, 2), v);
+        let v: Option<BTreeSet<u8>> = from_slice(&[0xd9, 0x01, 0x02, 0x9f, 0x01, 0xff]).unwrap();
+        assert_eq!(Some(vec![1].into_iter().collect()), v);
+    }
+
+    #[test]
+    fn test_unique() {
+        // 258([[1], 2, [1]])
+        let data = [0xd9, 0x01, 0x02, 0x83, 0x81, 0x01, 0x02, 0x81, 0x01];
+        let v: Vec<serde::de::IgnoredAny> = from_slice(&data).unwrap();
+        assert_eq!(3, v.len());
+
+        let cfg = Config::default().unique_sets(true);
+        let mut de = Deserializer::new_with_config(&data, cfg);
+        let err = Vec::<serde::de::IgnoredAny>::deserialize(&mut de).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::DuplicateElement));
+
+        // untagged arrays may repeat elements
+        let mut de = Deserializer::new_with_config(&data[3..], cfg);
+        assert!(Vec::<serde::de::IgnoredAny>::deserialize(&mut de).is_ok());
+        let mut de = Deserializer::new_with_config(&[0xd9, 0x01, 0x02, 0x82, 0x01, 0x02], cfg);
+        assert!(BTreeSet::<u8>::deserialize(&mut de).is_ok());
+
+        // large sets, the duplicate is last
+        let mut items: Vec<u32> = (0..50_000).collect();
+        let data = to_vec(&Set(&items)).unwrap();
+        let mut de = Deserializer::new_with_config(&data, cfg);
+        assert_eq!(items, Vec::<u32>::deserialize(&mut de).unwrap());
+        items.push(7);
+        let data = to_vec(&Set(&items)).unwrap();
+        let mut de = Deserializer::new_with_config(&data, cfg);
+        assert!(Vec::<u32>::deserialize(&mut de).is_err());
+    }
+}
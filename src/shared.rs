@@ -0,0 +1,345 @@
+//! Shared values, tags 28 and 29 (<http://cbor.schmorp.de/value-sharing>).
+//!
+//! [`Shared`] wraps an `Rc` or `Arc`. With [`Config::shared_values`], the
+//! first occurrence of a pointer is written as tag 28 over the value, and
+//! later occurrences as tag 29 over its index. Decoding rebuilds the
+//! sharing, so that both occurrences point to the same allocation again.
+//! At most [`Config::max_shared_values`] values are shared per document.
+//!
+//! Without the option, and in other serde formats, the value is written as
+//! is.
+//!
+//! ```
+//! use minicbor_ser::shared::Shared;
+//! use minicbor_ser::{from_slice, to_vec_cfg, Config};
+//! use std::sync::Arc;
+//!
+//! let limits = Arc::new(vec![10u32, 20]);
+//! let pair = (Shared(limits.clone()), Shared(limits));
+//! let data = to_vec_cfg(&pair, Config::default().shared_values(true)).unwrap();
+//! assert_eq!(data, [0x82, 0xd8, 0x1c, 0x82, 0x0a, 0x14, 0xd8, 0x1d, 0x00]);
+//!
+//! let (a, b): (Shared<Arc<Vec<u32>>>, Shared<Arc<Vec<u32>>>) = from_slice(&data).unwrap();
+//! assert!(Arc::ptr_eq(&a.0, &b.0));
+//! ```
+//!
+//! [`Config::shared_values`]: crate::Config::shared_values
+//! [`Config::max_shared_values`]: crate::Config::max_shared_values
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Unexpected, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::any::Any;
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// A value that may be referenced later.
+pub(crate) const SHAREABLE: u64 = 28;
+/// A reference to a shareable value.
+pub(crate) const SHARED_REF: u64 = 29;
+
+/// The newtype name shared values deserialize with.
+pub(crate) const SHARED_NAME: &str = "@@SHARED@@";
+
+/// The number of shared values per document, if not configured.
+pub(crate) const DEFAULT_MAX: usize = 1 << 16;
+
+/// An `Rc` or `Arc` whose sharing is kept when encoded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Shared<P>(pub P);
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A reference counted pointer, `Rc<T>` or `Arc<T>`.
+pub trait Pointer: Clone + Deref + sealed::Sealed + 'static {
+    #[doc(hidden)]
+    fn new(value: Self::Target) -> Self
+    where
+        Self::Target: Sized;
+}
+
+impl<T: 'static> sealed::Sealed for Rc<T> {}
+
+impl<T: 'static> Pointer for Rc<T> {
+    fn new(value: T) -> Self {
+        Rc::new(value)
+    }
+}
+
+impl<T: 'static> sealed::Sealed for Arc<T> {}
+
+impl<T: 'static> Pointer for Arc<T> {
+    fn new(value: T) -> Self {
+        Arc::new(value)
+    }
+}
+
+/// The shared values of a document being decoded, owned by its deserializer.
+/// A slot is `None` while its value is decoded.
+pub(crate) struct Table {
+    values: Vec<Option<Box<dyn Any>>>,
+    max: usize,
+}
+
+impl Table {
+    pub(crate) fn new(max: usize) -> Self {
+        Table {
+            values: Vec::new(),
+            max,
+        }
+    }
+}
+
+thread_local! {
+    /// The table of the deserializer whose shared value is being visited.
+    static ACTIVE: RefCell<Option<Table>> = const { RefCell::new(None) };
+}
+
+/// Hand `table` to the visitors `f` calls, and take it back when it returns.
+///
+/// A deserializer used inside `f` lends its own table and restores this one.
+pub(crate) fn lend<R>(table: Table, f: impl FnOnce() -> R) -> (R, Table) {
+    let outer = ACTIVE.with(|a| a.replace(Some(table)));
+    let result = f();
+    let table = ACTIVE.with(|a| a.replace(outer));
+    (result, table.expect("shared value table taken by a visitor"))
+}
+
+/// The pointee is written as is, the CBOR serializer writes tag 28 or 29
+/// around it with [`Config::shared_values`](crate::Config::shared_values).
+/// It recognizes a pointer by the address of its pointee.
+impl<P> Serialize for Shared<P>
+where
+    P: Pointer,
+    P::Target: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(SHARED_NAME, self.0.deref())
+    }
+}
+
+impl<'de, P> Deserialize<'de> for Shared<P>
+where
+    P: Pointer,
+    P::Target: Deserialize<'de> + Sized,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(SHARED_NAME, SharedVisitor(PhantomData))
+    }
+}
+
+struct SharedVisitor<P>(PhantomData<P>);
+
+impl<'de, P> Visitor<'de> for SharedVisitor<P>
+where
+    P: Pointer,
+    P::Target: Deserialize<'de> + Sized,
+{
+    type Value = Shared<P>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a shared value")
+    }
+
+    /// A value that isn't shared.
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        P::Target::deserialize(deserializer).map(|v| Shared(P::new(v)))
+    }
+
+    /// `[28, value]` or `[29, index]`.
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let tag: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        match tag {
+            SHAREABLE => {
+                let slot = reserve().map_err(de::Error::custom)?;
+                let value: P::Target = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                let p = P::new(value);
+                if let Some(slot) = slot {
+                    fill(slot, Box::new(p.clone()));
+                }
+                Ok(Shared(p))
+            }
+            SHARED_REF => {
+                let index: u64 = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                lookup(index).map(Shared).map_err(de::Error::custom)
+            }
+            _ => Err(de::Error::invalid_value(
+                Unexpected::Unsigned(tag),
+                &"tag 28 or 29",
+            )),
+        }
+    }
+}
+
+/// Take the next index of the lent table, if there is one.
+fn reserve() -> Result<Option<usize>, &'static str> {
+    ACTIVE.with(|a| match a.borrow_mut().as_mut() {
+        None => Ok(None),
+        Some(table) => {
+            if table.values.len() >= table.max {
+                return Err("too many shared values");
+            }
+            table.values.push(None);
+            Ok(Some(table.values.len() - 1))
+        }
+    })
+}
+
+fn fill(index: usize, value: Box<dyn Any>) {
+    ACTIVE.with(|a| {
+        if let Some(table) = a.borrow_mut().as_mut() {
+            table.values[index] = Some(value);
+        }
+    })
+}
+
+fn lookup<P: Pointer>(index: u64) -> Result<P, &'static str> {
+    ACTIVE.with(|a| {
+        let a = a.borrow();
+        let table = a
+            .as_ref()
+            .ok_or("shared value reference outside a document")?;
+        let slot = usize::try_from(index)
+            .ok()
+            .and_then(|i| table.values.get(i))
+            .ok_or("reference to an unknown shared value")?;
+        let value = slot
+            .as_ref()
+            .ok_or("reference to a shared value that contains it")?;
+        value
+            .downcast_ref::<P>()
+            .cloned()
+            .ok_or("reference to a shared value of another type")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::de::Deserializer;
+    use crate::{from_slice, to_vec, to_vec_cfg, Config};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Tree {
+        name: String,
+        children: Vec<Shared<Rc<Tree>>>,
+    }
+
+    fn leaf(name: &str) -> Rc<Tree> {
+        Rc::new(Tree {
+            name: name.to_string(),
+            children: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn test_shared() {
+        let a = leaf("a");
+        let b = Rc::new(Tree {
+            name: "b".to_string(),
+            children: vec![Shared(a.clone())],
+        });
+        let root = Tree {
+            name: "root".to_string(),
+            children: vec![Shared(b.clone()), Shared(a), Shared(b)],
+        };
+        let data = to_vec_cfg(&root, Config::default().shared_values(true)).unwrap();
+        assert!(data.len() < to_vec(&root).unwrap().len());
+        // b is 0, a is 1
+        assert_eq!(
+            &data[data.len() - 6..],
+            [0xd8, 0x1d, 0x01, 0xd8, 0x1d, 0x00]
+        );
+
+        let back: Tree = from_slice(&data).unwrap();
+        assert_eq!(root, back);
+        let (b, a) = (&back.children[0].0, &back.children[1].0);
+        assert!(Rc::ptr_eq(&b.children[0].0, a));
+        assert!(Rc::ptr_eq(b, &back.children[2].0));
+
+        // copies without the option
+        let back: Tree = from_slice(&to_vec(&root).unwrap()).unwrap();
+        assert!(!Rc::ptr_eq(&back.children[0].0, &back.children[2].0));
+    }
+
+    #[test]
+    fn test_invalid() {
+        type Pair = (Shared<Rc<u8>>, Shared<Rc<u8>>);
+        // unknown index, a reference to itself, another type
+        assert!(from_slice::<Pair>(&[0x82, 0xd8, 0x1c, 0x01, 0xd8, 0x1d, 0x01]).is_err());
+        assert!(from_slice::<Shared<Rc<Vec<Shared<Rc<u8>>>>>>(&[
+            0xd8, 0x1c, 0x81, 0xd8, 0x1d, 0x00
+        ])
+        .is_err());
+        assert!(from_slice::<(Shared<Rc<u8>>, Shared<Arc<u8>>)>(&[
+            0x82, 0xd8, 0x1c, 0x01, 0xd8, 0x1d, 0x00
+        ])
+        .is_err());
+
+        let data = [0x82, 0xd8, 0x1c, 0x01, 0xd8, 0x1c, 0x02];
+        assert!(from_slice::<Pair>(&data).is_ok());
+        let mut de = Deserializer::new_with_config(&data, Config::default().max_shared_values(1));
+        assert!(Pair::deserialize(&mut de).is_err());
+    }
+
+    #[test]
+    fn test_instances() {
+        // each deserializer has its own table: 28(1) 29(0) and 28(2)
+        let first = [0xd8, 0x1c, 0x01, 0xd8, 0x1d, 0x00];
+        let second = [0xd8, 0x1c, 0x02];
+        let mut de1 = Deserializer::new(&first);
+        let mut de2 = Deserializer::new(&second);
+        let two = Shared::<Rc<u8>>::deserialize(&mut de2).unwrap();
+        let one = Shared::<Rc<u8>>::deserialize(&mut de1).unwrap();
+        let again = Shared::<Rc<u8>>::deserialize(&mut de1).unwrap();
+        assert_eq!((*two.0, *one.0), (2, 1));
+        assert!(Rc::ptr_eq(&one.0, &again.0));
+
+        // a serializer used while another one writes doesn't share
+        struct Nested(Rc<u8>);
+        impl Serialize for Nested {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let data = to_vec(&Shared(self.0.clone())).unwrap();
+                serializer.serialize_bytes(&data)
+            }
+        }
+        let rc = Rc::new(5u8);
+        let cfg = Config::default().shared_values(true);
+        let data = to_vec_cfg(&(Shared(rc.clone()), Nested(rc)), cfg).unwrap();
+        assert_eq!(data, [0x82, 0xd8, 0x1c, 0x05, 0x41, 0x05]);
+
+        // a serializer created with the option shares too
+        let mut ser = crate::ser::Serializer::new_with_config(Vec::new(), cfg);
+        let rc = Rc::new(5u8);
+        (Shared(rc.clone()), Shared(rc)).serialize(&mut ser).unwrap();
+        assert_eq!(
+            ser.encoder.into_writer(),
+            [0x82, 0xd8, 0x1c, 0x05, 0xd8, 0x1d, 0x00]
+        );
+    }
+}
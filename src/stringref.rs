@@ -0,0 +1,213 @@
+//! String references, tags 25 and 256 (<http://cbor.schmorp.de/stringref>).
+//!
+//! Inside a namespace (tag 256) every definite length text or byte string
+//! that is long enough gets the next index, in the order of the document.
+//! A later equal string can then be written as tag 25 over its index.
+
+#[cfg(feature = "alloc")]
+use crate::lib::*;
+
+/// A reference to an earlier string of the namespace.
+pub(crate) const REF: u64 = 25;
+/// A new namespace for the item it encloses.
+pub(crate) const NAMESPACE: u64 = 256;
+
+/// Whether a string of `len` bytes gets index `next`. Shorter strings are
+/// not indexed, a reference to them would not be shorter.
+#[cfg(feature = "alloc")]
+pub(crate) fn indexed(len: usize, next: u64) -> bool {
+    let min = match next {
+        0..=23 => 3,
+        24..=0xff => 4,
+        0x100..=0xffff => 5,
+        0x1_0000..=0xffff_ffff => 7,
+        _ => 11,
+    };
+    len >= min
+}
+
+/// The strings an encoder has indexed.
+#[cfg(feature = "alloc")]
+#[derive(Default)]
+pub(crate) struct Strings {
+    text: BTreeMap<Vec<u8>, u64>,
+    bytes: BTreeMap<Vec<u8>, u64>,
+    next: u64,
+}
+
+#[cfg(feature = "alloc")]
+impl Strings {
+    /// The index of an equal string written before, or `None` if `s` has to
+    /// be written, in which case it's indexed if long enough.
+    pub(crate) fn find(&mut self, text: bool, s: &[u8]) -> Option<u64> {
+        let map = if text {
+            &mut self.text
+        } else {
+            &mut self.bytes
+        };
+        if let Some(index) = map.get(s) {
+            return Some(*index);
+        }
+        if indexed(s.len(), self.next) {
+            map.insert(s.to_vec(), self.next);
+            self.next += 1;
+        }
+        None
+    }
+}
+
+/// The strings a decoder has indexed in a namespace, which ends at `end`.
+#[cfg(feature = "alloc")]
+pub(crate) struct Namespace<'de> {
+    pub(crate) end: usize,
+    /// Whether a string is text, and its bytes.
+    pub(crate) strings: Vec<(bool, &'de [u8])>,
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+    use crate::de::Deserializer;
+    use crate::error::de::ErrorKind;
+    use crate::{from_slice, to_vec, to_vec_cfg, Config, Tag, Tagged};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Point<'a> {
+        name: &'a str,
+        #[serde(with = "serde_bytes_ref")]
+        data: &'a [u8],
+        value: u128,
+    }
+
+    mod serde_bytes_ref {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(v: &[u8], s: S) -> Result<S::Ok, S::Error> {
+            s.serialize_bytes(v)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<&'de [u8], D::Error> {
+            <&[u8]>::deserialize(d)
+        }
+    }
+
+    #[test]
+    fn test_indexed() {
+        assert!(!indexed(2, 0));
+        assert!(indexed(3, 23));
+        assert!(!indexed(3, 24));
+        assert!(indexed(4, 255));
+        assert!(!indexed(6, 0x1_0000));
+        assert!(indexed(11, 1 << 32));
+    }
+
+    #[test]
+    fn test_string_refs() {
+        let cfg = Config::default().string_refs(true);
+        let data = to_vec_cfg(&["name", "ab", "name", "ab"], cfg).unwrap();
+        // 256(["name", "ab", 25(0), "ab"])
+        assert_eq!(
+            data,
+            [
+                0xd9, 0x01, 0x00, 0x84, 0x64, b'n', b'a', b'm', b'e', 0x62, b'a', b'b', 0xd8, 0x19,
+                0x00, 0x62, b'a', b'b'
+            ]
+        );
+        assert_eq!(
+            vec!["name", "ab", "name", "ab"],
+            from_slice::<Vec<&str>>(&data).unwrap()
+        );
+
+        // keys borrow from the input, bignums and byte strings share the index
+        let points = vec![
+            Point {
+                name: "first",
+                data: b"first",
+                value: u128::MAX,
+            };
+            3
+        ];
+        let data = to_vec_cfg(&points, cfg).unwrap();
+        assert!(data.len() < to_vec(&points).unwrap().len());
+        let back: Vec<Point> = from_slice(&data).unwrap();
+        assert_eq!(points, back);
+        let start = data.as_ptr() as usize..data.as_ptr() as usize + data.len();
+        assert!(start.contains(&(back[2].name.as_ptr() as usize)));
+        assert!(start.contains(&(back[2].data.as_ptr() as usize)));
+    }
+
+    #[test]
+    fn test_top_level() {
+        let cfg = Config::default().string_refs(true);
+        let data = to_vec_cfg(&5u8, cfg).unwrap();
+        assert_eq!(data, [0xd9, 0x01, 0x00, 0x05]);
+        assert_eq!(5u8, from_slice(&data).unwrap());
+        fn round_trip<T>(v: T, cfg: Config)
+        where
+            T: Serialize + for<'de> Deserialize<'de> + PartialEq + core::fmt::Debug,
+        {
+            let data = to_vec_cfg(&v, cfg).unwrap();
+            assert_eq!(&data[..3], [0xd9, 0x01, 0x00]);
+            assert_eq!(v, from_slice::<T>(&data).unwrap());
+        }
+        round_trip(-7i64, cfg);
+        round_trip(1.5f64, cfg);
+        round_trip(0.25f32, cfg);
+        round_trip(true, cfg);
+        round_trip('x', cfg);
+        round_trip((), cfg);
+        round_trip(u128::MAX, cfg);
+        round_trip(i128::MIN, cfg);
+        round_trip(Some(3u16), cfg);
+        round_trip(Tagged::new(1, 5u8), cfg);
+        round_trip(Tagged::new(1, String::from("hello")), cfg);
+        round_trip(Tag::<1, _>(9u32), cfg);
+    }
+
+    #[test]
+    fn test_namespaces() {
+        // ["aaa", 256(["bbb", 25(0)]), 25(0)] inside a namespace
+        let data = [
+            0xd9, 0x01, 0x00, 0x83, 0x63, b'a', b'a', b'a', 0xd9, 0x01, 0x00, 0x82, 0x63, b'b',
+            b'b', b'b', 0xd8, 0x19, 0x00, 0xd8, 0x19, 0x00,
+        ];
+        let v: (&str, Vec<&str>, &str) = from_slice(&data).unwrap();
+        assert_eq!(("aaa", vec!["bbb", "bbb"], "aaa"), v);
+
+        // skipped strings are indexed too
+        #[derive(Deserialize)]
+        struct Second<'a> {
+            b: &'a str,
+        }
+        let second: BTreeMap<_, _> = vec![("b", "xyz")].into_iter().collect();
+        let data =
+            to_vec_cfg(&(("a", "xyz"), second), Config::default().string_refs(true)).unwrap();
+        assert_eq!(&data[data.len() - 3..], [0xd8, 0x19, 0x00]);
+        let v: (serde::de::IgnoredAny, Second) = from_slice(&data).unwrap();
+        assert_eq!("xyz", v.1.b);
+
+        // unknown index, and a reference outside a namespace
+        let err = from_slice::<Vec<&str>>(&[0xd9, 0x01, 0x00, 0x81, 0xd8, 0x19, 0x00]).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::UnknownReference));
+        let err = from_slice::<&str>(&[0xd8, 0x19, 0x00]).unwrap_err();
+        assert!(matches!(err.kind, ErrorKind::UnknownReference));
+        let mut de = Deserializer::new(&[0xd9, 0x01, 0x00, 0x63, b'a', b'b', b'c']);
+        assert_eq!("abc", <&str>::deserialize(&mut de).unwrap());
+    }
+
+    #[test]
+    fn test_ip_octets() {
+        use crate::lib::net::{IpAddr, Ipv4Addr};
+        let ip = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+        let cfg = Config::default().human_readable(false).string_refs(true);
+        let data = to_vec_cfg(&[ip, ip], cfg).unwrap();
+        // 256([52(h'c0000201'), 52(25(0))])
+        assert_eq!(&data[data.len() - 5..], [0xd8, 0x34, 0xd8, 0x19, 0x00]);
+        let mut de = Deserializer::new_with_config(&data, cfg);
+        assert_eq!([ip, ip], <[IpAddr; 2]>::deserialize(&mut de).unwrap());
+    }
+}
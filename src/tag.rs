@@ -0,0 +1,265 @@
+//! CBOR tags.
+//!
+//! [`Tagged`] carries a tag number next to its value, [`Tag`] fixes the
+//! number in the type. Both serialize as the tag followed by the value,
+//! other serde formats see a `(tag, value)` tuple.
+//!
+//! ```
+//! use minicbor_ser::tag::{Tag, Tagged};
+//! use minicbor_ser::{from_slice, to_vec};
+//!
+//! let data = to_vec(&Tagged::new(32, "http://a.b")).unwrap();
+//! assert_eq!(&data[..2], &[0xd8, 0x20]);
+//! let url: Tag<32, &str> = from_slice(&data).unwrap();
+//! assert_eq!(url.0, "http://a.b");
+//! ```
+//!
+//! An enum renamed to `"@@TAGGED@@"` picks its variants by tag number. Each
+//! newtype variant is renamed to its number, and written as that tag over
+//! its value. Other serde formats see the enum as usual.
+//!
+//! ```
+//! use minicbor_ser::{from_slice, to_vec};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! #[serde(rename = "@@TAGGED@@")]
+//! enum Cose {
+//!     #[serde(rename = "17")]
+//!     Mac0(Vec<u8>),
+//!     #[serde(rename = "18")]
+//!     Sign1(Vec<u8>),
+//! }
+//!
+//! let data = to_vec(&Cose::Sign1(vec![1])).unwrap();
+//! assert_eq!(data, [0xd2, 0x81, 0x01]);
+//! assert_eq!(Cose::Sign1(vec![1]), from_slice(&data).unwrap());
+//! ```
+
+use crate::lib::*;
+use minicbor::data::Tag as DataTag;
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Unexpected, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+/// Self-described CBOR (RFC 8949 §3.4.6), the "magic number" of CBOR files.
+pub(crate) const SELF_DESCRIBE: u64 = 55799;
+
+/// Binary UUID (RFC 9562), over a 16 byte string.
+pub(crate) const UUID: u64 = 37;
+
+/// The newtype name tagged values serialize with.
+pub(crate) const TAGGED_NAME: &str = "@@TAGGED@@";
+
+/// The newtype name of a value that may be tagged, visited as `[tag, value]`
+/// when it is and on its own otherwise.
+#[cfg(feature = "url")]
+pub(crate) const MAYBE_TAGGED_NAME: &str = "@@MAYBE_TAGGED@@";
+
+/// The number of a tag as decoded by minicbor.
+pub(crate) fn number(tag: DataTag) -> u64 {
+    match tag {
+        DataTag::DateTime => 0x00,
+        DataTag::Timestamp => 0x01,
+        DataTag::PosBignum => 0x02,
+        DataTag::NegBignum => 0x03,
+        DataTag::Decimal => 0x04,
+        DataTag::Bigfloat => 0x05,
+        DataTag::ToBase64Url => 0x15,
+        DataTag::ToBase64 => 0x16,
+        DataTag::ToBase16 => 0x17,
+        DataTag::Cbor => 0x18,
+        DataTag::Uri => 0x20,
+        DataTag::Base64Url => 0x21,
+        DataTag::Base64 => 0x22,
+        DataTag::Regex => 0x23,
+        DataTag::Mime => 0x24,
+        DataTag::Unassigned(n) => n,
+    }
+}
+
+/// A value with any tag number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Tagged<T> {
+    pub tag: u64,
+    pub value: T,
+}
+
+impl<T> Tagged<T> {
+    pub fn new(tag: u64, value: T) -> Self {
+        Tagged { tag, value }
+    }
+}
+
+impl<T: Serialize> Serialize for Tagged<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(TAGGED_NAME, &(self.tag, &self.value))
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Tagged<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(TAGGED_NAME, TaggedVisitor(PhantomData))
+    }
+}
+
+struct TaggedVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for TaggedVisitor<T> {
+    type Value = Tagged<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a tagged value")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let (tag, value) = Deserialize::deserialize(deserializer)?;
+        Ok(Tagged { tag, value })
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let tag = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let value = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok(Tagged { tag, value })
+    }
+}
+
+/// A value with the tag number `N`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct Tag<const N: u64, T>(pub T);
+
+impl<const N: u64, T: Serialize> Serialize for Tag<N, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(TAGGED_NAME, &(N, &self.0))
+    }
+}
+
+impl<'de, const N: u64, T: Deserialize<'de>> Deserialize<'de> for Tag<N, T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let tagged = Tagged::<T>::deserialize(deserializer)?;
+        if tagged.tag != N {
+            return Err(de::Error::invalid_value(
+                Unexpected::Unsigned(tagged.tag),
+                &ExpectedTag(N),
+            ));
+        }
+        Ok(Tag(tagged.value))
+    }
+}
+
+struct ExpectedTag(u64);
+
+impl de::Expected for ExpectedTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "tag {}", self.0)
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{from_slice, to_vec};
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+
+    #[test]
+    fn test_tagged() {
+        let data = to_vec(&Tagged::new(1, 1_600_000_000u32)).unwrap();
+        assert_eq!(data, [0xc1, 0x1a, 0x5f, 0x5e, 0x10, 0x00]);
+        let v: Tagged<u32> = from_slice(&data).unwrap();
+        assert_eq!(v, Tagged::new(1, 1_600_000_000));
+
+        let data = to_vec(&Tagged::new(1000, [1u8, 2])).unwrap();
+        assert_eq!(data, [0xd9, 0x03, 0xe8, 0x82, 0x01, 0x02]);
+        let v: Tagged<Vec<u8>> = from_slice(&data).unwrap();
+        assert_eq!(v, Tagged::new(1000, vec![1, 2]));
+
+        // nested tags
+        let value = Tagged::new(6, Tagged::new(7, "a"));
+        let data = to_vec(&value).unwrap();
+        assert_eq!(data, [0xc6, 0xc7, 0x61, 0x61]);
+        assert_eq!(value, from_slice(&data).unwrap());
+
+        assert!(from_slice::<Tagged<u8>>(&[0x01]).is_err());
+    }
+
+    #[test]
+    fn test_tag() {
+        let data = to_vec(&Tag::<55799, _>(true)).unwrap();
+        assert_eq!(data, [0xd9, 0xd9, 0xf7, 0xf5]);
+
+        let data = to_vec(&Tag::<100, _>(-1i8)).unwrap();
+        assert_eq!(data, [0xd8, 0x64, 0x20]);
+        let v: Tag<100, i8> = from_slice(&data).unwrap();
+        assert_eq!(v, Tag(-1));
+        assert!(from_slice::<Tag<101, i8>>(&data).is_err());
+    }
+
+    #[test]
+    fn test_enum() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        #[serde(rename = "@@TAGGED@@")]
+        enum Payload {
+            #[serde(rename = "17")]
+            Mac(u8),
+            #[serde(rename = "1000")]
+            Text(String),
+        }
+        let data = to_vec(&Payload::Text("a".to_string())).unwrap();
+        assert_eq!(data, [0xd9, 0x03, 0xe8, 0x61, 0x61]);
+        assert_eq!(Payload::Text("a".to_string()), from_slice(&data).unwrap());
+        let v: Vec<Payload> = from_slice(&[0x82, 0xd1, 0x01, 0xd1, 0x02]).unwrap();
+        assert_eq!(v, [Payload::Mac(1), Payload::Mac(2)]);
+
+        // unknown tags, a missing tag, a variant that isn't a tag number
+        assert!(from_slice::<Payload>(&[0xd2, 0x01]).is_err());
+        assert!(from_slice::<Payload>(&[0x01]).is_err());
+        #[derive(serde::Serialize)]
+        #[serde(rename = "@@TAGGED@@")]
+        enum Named {
+            A(u8),
+        }
+        assert!(to_vec(&Named::A(1)).is_err());
+    }
+
+    #[test]
+    fn test_in_struct() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Foo {
+            a: Tagged<u8>,
+            b: Option<Tag<11, u8>>,
+        }
+        let foo = Foo {
+            a: Tagged::new(10, 1),
+            b: Some(Tag(4)),
+        };
+        let data = to_vec(&foo).unwrap();
+        assert_eq!(
+            data,
+            [0xa2, 0x61, 0x61, 0xca, 0x01, 0x61, 0x62, 0xcb, 0x04]
+        );
+        assert_eq!(foo, from_slice(&data).unwrap());
+    }
+}
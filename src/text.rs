@@ -0,0 +1,202 @@
+//! Text with a meaning, tags 32 to 36 (RFC 8949 §3.4.5.3).
+//!
+//! [`Uri`], [`Base64Url`], [`Base64`], [`Regex`] and [`Mime`] write their
+//! text as the tag over a text string, other serde formats see a
+//! `(tag, text)` tuple. URIs and base64 are checked when decoding, the
+//! content of the other tags is taken as is. With the `url` feature,
+//! [`crate::url`] does the same for `url::Url`.
+//!
+//! ```
+//! use minicbor_ser::text::Uri;
+//! use minicbor_ser::{from_slice, to_vec};
+//!
+//! let data = to_vec(&Uri("http://a.b/c")).unwrap();
+//! assert_eq!(&data[..2], [0xd8, 0x20]);
+//! let uri: Uri<&str> = from_slice(&data).unwrap();
+//! assert_eq!(uri.0, "http://a.b/c");
+//!
+//! assert!(from_slice::<Uri<&str>>(&to_vec(&Uri("a b")).unwrap()).is_err());
+//! ```
+
+use crate::tag::Tag;
+use serde::de::{self, Deserialize, Deserializer, Unexpected};
+use serde::ser::{Serialize, Serializer};
+
+/// URI reference (RFC 3986).
+pub(crate) const URI: u64 = 32;
+/// Base64url without padding (RFC 4648 §5).
+pub(crate) const BASE64URL: u64 = 33;
+/// Base64 with padding (RFC 4648 §4).
+pub(crate) const BASE64: u64 = 34;
+/// Regular expression.
+pub(crate) const REGEX: u64 = 35;
+/// MIME message (RFC 2045).
+pub(crate) const MIME: u64 = 36;
+
+macro_rules! text_tag {
+    ($(#[$doc:meta])* $name:ident, $tag:expr, $check:expr, $expected:expr) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+        pub struct $name<T>(pub T);
+
+        impl<T: Serialize> Serialize for $name<T> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                Tag::<$tag, _>(&self.0).serialize(serializer)
+            }
+        }
+
+        impl<'de, T> Deserialize<'de> for $name<T>
+        where
+            T: Deserialize<'de> + AsRef<str>,
+        {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let Tag(text) = Tag::<$tag, T>::deserialize(deserializer)?;
+                let check: fn(&str) -> bool = $check;
+                if !check(text.as_ref()) {
+                    return Err(de::Error::invalid_value(
+                        Unexpected::Str(text.as_ref()),
+                        &$expected,
+                    ));
+                }
+                Ok($name(text))
+            }
+        }
+    };
+}
+
+text_tag!(
+    /// A URI reference, tag 32.
+    Uri,
+    URI,
+    uri,
+    "a URI reference"
+);
+text_tag!(
+    /// Base64url encoded data without padding, tag 33.
+    Base64Url,
+    BASE64URL,
+    base64url,
+    "base64url without padding"
+);
+text_tag!(
+    /// Base64 encoded data with padding, tag 34.
+    Base64,
+    BASE64,
+    base64,
+    "base64 with padding"
+);
+text_tag!(
+    /// A regular expression, tag 35. The syntax isn't checked.
+    Regex,
+    REGEX,
+    |_| true,
+    "a regular expression"
+);
+text_tag!(
+    /// A MIME message, tag 36. The content isn't checked.
+    Mime,
+    MIME,
+    |_| true,
+    "a MIME message"
+);
+
+/// Whether `s` only has the characters of a URI reference, with valid
+/// percent escapes. The structure isn't checked.
+pub(crate) fn uri(s: &str) -> bool {
+    let b = s.as_bytes();
+    let mut i = 0;
+    while i < b.len() {
+        match b[i] {
+            b'%' => {
+                match b.get(i + 1..i + 3) {
+                    Some([hi, lo]) if hi.is_ascii_hexdigit() && lo.is_ascii_hexdigit() => {}
+                    _ => return false,
+                }
+                i += 3;
+                continue;
+            }
+            c if c.is_ascii_alphanumeric() || b"-._~:/?#[]@!$&'()*+,;=".contains(&c) => {}
+            _ => return false,
+        }
+        i += 1;
+    }
+    true
+}
+
+fn base64url(s: &str) -> bool {
+    s.len() % 4 != 1 && alphabet(s, b"-_")
+}
+
+fn base64(s: &str) -> bool {
+    let data = s.trim_end_matches('=');
+    s.len().is_multiple_of(4)
+        && s.len() - data.len() <= 2
+        && data.len() % 4 != 1
+        && alphabet(data, b"+/")
+}
+
+/// Whether `s` only has alphanumeric characters and the two of its alphabet.
+fn alphabet(s: &str, extra: &[u8; 2]) -> bool {
+    s.bytes().all(|c| c.is_ascii_alphanumeric() || extra.contains(&c))
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::*;
+    use crate::{from_slice, to_vec};
+
+    #[test]
+    fn test_checks() {
+        assert!(uri("https://example.com/a%20b?q=1#x"));
+        assert!(uri("../relative"));
+        assert!(!uri("a b"));
+        assert!(!uri("%2"));
+        assert!(!uri("%zz"));
+
+        assert!(base64url("aGVsbG8"));
+        assert!(base64url("-_-_"));
+        assert!(!base64url("aGVsbG8="));
+        assert!(!base64url("a+/b"));
+        assert!(!base64url("abcde"));
+
+        assert!(base64("aGVsbG8="));
+        assert!(base64("a+/b"));
+        assert!(!base64("aGVsbG8"));
+        assert!(!base64("a==="));
+        assert!(!base64("-_-_"));
+    }
+
+    #[test]
+    fn test_text_tags() {
+        let data = to_vec(&Base64Url("aGk")).unwrap();
+        assert_eq!(data, [0xd8, 0x21, 0x63, b'a', b'G', b'k']);
+        assert_eq!(Base64Url("aGk"), from_slice(&data).unwrap());
+        assert!(from_slice::<Base64<String>>(&data).is_err());
+
+        let data = to_vec(&Mime("Content-Type: text/plain\n\nhi")).unwrap();
+        assert_eq!(&data[..2], [0xd8, 0x24]);
+        let mime: Mime<String> = from_slice(&data).unwrap();
+        assert!(mime.0.ends_with("hi"));
+
+        let data = to_vec(&(Regex("a+"), Base64("aGk="))).unwrap();
+        let back: (Regex<&str>, Base64<Cow<str>>) = from_slice(&data).unwrap();
+        assert_eq!(back, (Regex("a+"), Base64(Cow::Borrowed("aGk="))));
+
+        // the tags stand on their own in self-describing targets
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        #[serde(untagged)]
+        enum Any {
+            Text(String),
+        }
+        let v: Vec<Any> = from_slice(&data).unwrap();
+        assert_eq!(v[1], Any::Text("aGk=".to_string()));
+    }
+}
@@ -0,0 +1,488 @@
+//! Typed arrays (RFC 8746), tags 64 to 87.
+//!
+//! A typed array packs numbers of one type into a single byte string, in
+//! big or little endian. [`TypedArray`] writes a slice of `u8` to `u64`,
+//! `i8` to `i64`, `f32` or `f64` that way, instead of an array with a
+//! header per element.
+//!
+//! Typed arrays are accepted wherever a sequence is expected, so a plain
+//! `Vec<f32>` reads them too. Half float arrays are widened. Decoding a
+//! [`TypedArray`] borrows the elements from the input when their endianness
+//! is native and the bytes are aligned, and copies them otherwise.
+//!
+//! ```
+//! use minicbor_ser::typed_array::{Endian, TypedArray};
+//! use minicbor_ser::{from_slice, to_vec};
+//!
+//! let frame = vec![1u16, 2, 3];
+//! let data = to_vec(&TypedArray::new(&frame[..]).endian(Endian::Big)).unwrap();
+//! assert_eq!(data, [0xd8, 0x41, 0x46, 0, 1, 0, 2, 0, 3]);
+//!
+//! let back: Vec<u16> = from_slice(&data).unwrap();
+//! assert_eq!(back, frame);
+//! let back: TypedArray<u16> = from_slice(&data).unwrap();
+//! assert_eq!(back.as_slice(), frame);
+//! ```
+
+use crate::lib::*;
+
+/// Byte order of the elements of a typed array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Endian {
+    Big,
+    #[default]
+    Little,
+}
+
+impl Endian {
+    /// The byte order of the target.
+    #[cfg(target_endian = "big")]
+    pub const NATIVE: Endian = Endian::Big;
+    /// The byte order of the target.
+    #[cfg(target_endian = "little")]
+    pub const NATIVE: Endian = Endian::Little;
+}
+
+/// What a typed array tag holds.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Layout {
+    pub(crate) kind: Kind,
+    pub(crate) endian: Endian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F16,
+    F32,
+    F64,
+}
+
+impl Kind {
+    pub(crate) fn size(self) -> usize {
+        match self {
+            Kind::U8 | Kind::I8 => 1,
+            Kind::U16 | Kind::I16 | Kind::F16 => 2,
+            Kind::U32 | Kind::I32 | Kind::F32 => 4,
+            Kind::U64 | Kind::I64 | Kind::F64 => 8,
+        }
+    }
+}
+
+/// The layout of typed array `tag`. `None` for other tags, and for the
+/// reserved tag 76 and 128 bit floats, which have no Rust type.
+///
+/// The tag is `0b010_f_s_e_ll`: float, signed, little endian, and the
+/// element width.
+pub(crate) fn layout(tag: u64) -> Option<Layout> {
+    if !(64..=87).contains(&tag) {
+        return None;
+    }
+    let endian = if tag & 0b100 == 0 {
+        Endian::Big
+    } else {
+        Endian::Little
+    };
+    let kind = match ((tag >> 3) & 0b11, tag & 0b11) {
+        // uint8 "little endian" is the clamped variant
+        (0b00, 0) => Kind::U8,
+        (0b00, 1) => Kind::U16,
+        (0b00, 2) => Kind::U32,
+        (0b00, 3) => Kind::U64,
+        // sint8 little endian is reserved
+        (0b01, 0) if endian == Endian::Little => return None,
+        (0b01, 0) => Kind::I8,
+        (0b01, 1) => Kind::I16,
+        (0b01, 2) => Kind::I32,
+        (0b01, 3) => Kind::I64,
+        (_, 0) => Kind::F16,
+        (_, 1) => Kind::F32,
+        (_, 2) => Kind::F64,
+        _ => return None,
+    };
+    Some(Layout { kind, endian })
+}
+
+#[cfg(feature = "alloc")]
+pub use self::array::{Element, TypedArray};
+
+#[cfg(feature = "alloc")]
+mod array {
+    use super::{layout, Endian};
+    use crate::lib::*;
+    use crate::tag::{Tagged, TAGGED_NAME};
+    use serde::de::{self, Deserialize, Deserializer, SeqAccess, Unexpected, Visitor};
+    use serde::ser::{Serialize, Serializer};
+
+    mod sealed {
+        pub trait Sealed {}
+    }
+
+    /// A number that can be an element of a [`TypedArray`].
+    pub trait Element: Copy + sealed::Sealed + 'static {
+        #[doc(hidden)]
+        const SIZE: usize;
+        /// The big endian tag.
+        #[doc(hidden)]
+        const TAG: u64;
+
+        #[doc(hidden)]
+        fn read(bytes: &[u8], endian: Endian) -> Self;
+        #[doc(hidden)]
+        fn write(self, out: &mut Vec<u8>, endian: Endian);
+    }
+
+    macro_rules! element {
+        ($($t:ty => $tag:expr;)*) => {$(
+            impl sealed::Sealed for $t {}
+
+            impl Element for $t {
+                const SIZE: usize = mem::size_of::<$t>();
+                const TAG: u64 = $tag;
+
+                fn read(bytes: &[u8], endian: Endian) -> Self {
+                    let mut b = [0; mem::size_of::<$t>()];
+                    b.copy_from_slice(bytes);
+                    match endian {
+                        Endian::Big => <$t>::from_be_bytes(b),
+                        Endian::Little => <$t>::from_le_bytes(b),
+                    }
+                }
+
+                fn write(self, out: &mut Vec<u8>, endian: Endian) {
+                    match endian {
+                        Endian::Big => out.extend_from_slice(&self.to_be_bytes()),
+                        Endian::Little => out.extend_from_slice(&self.to_le_bytes()),
+                    }
+                }
+            }
+        )*};
+    }
+
+    element! {
+        u8 => 64;
+        u16 => 65;
+        u32 => 66;
+        u64 => 67;
+        i8 => 72;
+        i16 => 73;
+        i32 => 74;
+        i64 => 75;
+        f32 => 81;
+        f64 => 82;
+    }
+
+    /// A slice of numbers written as one typed array.
+    ///
+    /// The elements are written in little endian unless set otherwise.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct TypedArray<'a, T: Element> {
+        values: Cow<'a, [T]>,
+        endian: Endian,
+    }
+
+    impl<'a, T: Element> TypedArray<'a, T> {
+        pub fn new<V>(values: V) -> Self
+        where
+            V: Into<Cow<'a, [T]>>,
+        {
+            TypedArray {
+                values: values.into(),
+                endian: Endian::default(),
+            }
+        }
+
+        /// Set the byte order the elements are written in.
+        pub fn endian(mut self, endian: Endian) -> Self {
+            self.endian = endian;
+            self
+        }
+
+        /// The byte order the elements were read in, or are written in.
+        pub fn byte_order(&self) -> Endian {
+            self.endian
+        }
+
+        pub fn as_slice(&self) -> &[T] {
+            &self.values
+        }
+
+        /// Whether the elements are borrowed from the input.
+        pub fn is_borrowed(&self) -> bool {
+            matches!(self.values, Cow::Borrowed(_))
+        }
+
+        pub fn into_vec(self) -> Vec<T> {
+            self.values.into_owned()
+        }
+
+        /// The tag the elements are written with.
+        fn tag(&self) -> u64 {
+            match (T::SIZE, self.endian) {
+                (1, _) | (_, Endian::Big) => T::TAG,
+                (_, Endian::Little) => T::TAG | 0b100,
+            }
+        }
+    }
+
+    impl<T: Element> ops::Deref for TypedArray<'_, T> {
+        type Target = [T];
+
+        fn deref(&self) -> &[T] {
+            &self.values
+        }
+    }
+
+    impl<T: Element> From<Vec<T>> for TypedArray<'_, T> {
+        fn from(values: Vec<T>) -> Self {
+            TypedArray::new(values)
+        }
+    }
+
+    impl<'a, T: Element> From<&'a [T]> for TypedArray<'a, T> {
+        fn from(values: &'a [T]) -> Self {
+            TypedArray::new(values)
+        }
+    }
+
+    impl<T: Element> Serialize for TypedArray<'_, T> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let mut bytes = Vec::with_capacity(self.values.len() * T::SIZE);
+            for v in self.values.iter() {
+                v.write(&mut bytes, self.endian);
+            }
+            Tagged::new(self.tag(), Bytes(Cow::Owned(bytes))).serialize(serializer)
+        }
+    }
+
+    impl<'de, T: Element> Deserialize<'de> for TypedArray<'de, T> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_newtype_struct(TAGGED_NAME, TypedArrayVisitor(PhantomData))
+        }
+    }
+
+    struct TypedArrayVisitor<T>(PhantomData<T>);
+
+    impl<'de, T: Element> Visitor<'de> for TypedArrayVisitor<T> {
+        type Value = TypedArray<'de, T>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a typed array")
+        }
+
+        fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_tuple(2, self)
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let tag: u64 = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+            // the byte order bit, or uint8 clamped
+            let endian = match layout(tag) {
+                Some(l) if tag & !0b100 == T::TAG => l.endian,
+                _ => return Err(de::Error::invalid_value(Unexpected::Unsigned(tag), &self)),
+            };
+            let Bytes(bytes) = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+            if !bytes.len().is_multiple_of(T::SIZE) {
+                return Err(de::Error::invalid_length(bytes.len(), &self));
+            }
+            let values = match bytes {
+                Cow::Borrowed(b) if endian == Endian::NATIVE || T::SIZE == 1 => match cast(b) {
+                    Some(values) => Cow::Borrowed(values),
+                    None => Cow::Owned(read(b, endian)),
+                },
+                b => Cow::Owned(read(&b, endian)),
+            };
+            Ok(TypedArray { values, endian })
+        }
+    }
+
+    fn read<T: Element>(bytes: &[u8], endian: Endian) -> Vec<T> {
+        bytes
+            .chunks_exact(T::SIZE)
+            .map(|b| T::read(b, endian))
+            .collect()
+    }
+
+    /// View native endian `bytes` as elements, if they are aligned.
+    fn cast<T: Element>(bytes: &[u8]) -> Option<&[T]> {
+        // SAFETY: `Element` is sealed and only implemented for integers and
+        // floats, for which every bit pattern is a valid value.
+        let (head, values, tail) = unsafe { bytes.align_to::<T>() };
+        if head.is_empty() && tail.is_empty() {
+            Some(values)
+        } else {
+            None
+        }
+    }
+
+    /// The byte string of a typed array, borrowed where the input allows.
+    struct Bytes<'a>(Cow<'a, [u8]>);
+
+    impl Serialize for Bytes<'_> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Bytes<'de> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_bytes(BytesVisitor)
+        }
+    }
+
+    struct BytesVisitor;
+
+    impl<'de> Visitor<'de> for BytesVisitor {
+        type Value = Bytes<'de>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a byte string")
+        }
+
+        fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(Bytes(Cow::Borrowed(v)))
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(Bytes(Cow::Owned(v.to_vec())))
+        }
+
+        fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(Bytes(Cow::Owned(v)))
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut bytes = Vec::new();
+            while let Some(b) = seq.next_element()? {
+                bytes.push(b);
+            }
+            Ok(Bytes(Cow::Owned(bytes)))
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+    use crate::{from_slice, to_vec};
+
+    #[test]
+    fn test_layout() {
+        let l = layout(70).unwrap();
+        assert_eq!((Kind::U32, Endian::Little), (l.kind, l.endian));
+        let l = layout(68).unwrap();
+        assert_eq!((Kind::U8, Endian::Little), (l.kind, l.endian));
+        let l = layout(84).unwrap();
+        assert_eq!((Kind::F16, Endian::Little), (l.kind, l.endian));
+        assert!(layout(63).is_none());
+        assert!(layout(76).is_none());
+        assert!(layout(87).is_none());
+        assert!(layout(88).is_none());
+    }
+
+    #[test]
+    fn test_typed_array() {
+        let v = vec![1.5f32, -2.0];
+        let data = to_vec(&TypedArray::new(&v[..])).unwrap();
+        assert_eq!(&data[..3], [0xd8, 0x55, 0x48]);
+        assert_eq!(&data[3..7], 1.5f32.to_le_bytes());
+        assert_eq!(v, from_slice::<Vec<f32>>(&data).unwrap());
+        assert_eq!(v, from_slice::<TypedArray<f32>>(&data).unwrap().into_vec());
+
+        let data = to_vec(&TypedArray::from(vec![-1i32, 2]).endian(Endian::Big)).unwrap();
+        assert_eq!(data, [0xd8, 0x4a, 0x48, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 2]);
+        assert_eq!(vec![-1, 2], from_slice::<Vec<i64>>(&data).unwrap());
+        let back: TypedArray<i32> = from_slice(&data).unwrap();
+        assert_eq!((Endian::Big, &[-1, 2][..]), (back.byte_order(), &back[..]));
+
+        // bytes are one tag for both byte orders
+        let data = to_vec(&TypedArray::new(&b"ab"[..])).unwrap();
+        assert_eq!(data, [0xd8, 0x40, 0x42, b'a', b'b']);
+        assert!(from_slice::<TypedArray<u8>>(&data).unwrap().is_borrowed());
+
+        // half floats, written by other encoders
+        let data = [0xd8, 0x50, 0x44, 0x3c, 0x00, 0xc0, 0x00];
+        assert_eq!(vec![1.0, -2.0], from_slice::<Vec<f64>>(&data).unwrap());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let data = to_vec(&TypedArray::new(vec![1u16])).unwrap();
+        assert!(from_slice::<TypedArray<i16>>(&data).is_err());
+        assert!(from_slice::<TypedArray<u32>>(&data).is_err());
+        // ragged bytes
+        assert!(from_slice::<Vec<u16>>(&[0xd8, 0x45, 0x43, 1, 0, 2]).is_err());
+        assert!(from_slice::<TypedArray<u16>>(&[0xd8, 0x45, 0x43, 1, 0, 2]).is_err());
+        // reserved
+        assert!(from_slice::<Vec<i8>>(&[0xd8, 0x4c, 0x41, 1]).is_err());
+    }
+
+    #[test]
+    fn test_borrowed() {
+        let v: Vec<u32> = (0..8).collect();
+        let data = to_vec(&TypedArray::new(&v[..]).endian(Endian::NATIVE)).unwrap();
+        // the payload is aligned at exactly one of four offsets
+        let mut buf = vec![0u8; data.len() + 3];
+        let mut borrowed = 0;
+        for pad in 0..4 {
+            buf[pad..pad + data.len()].copy_from_slice(&data);
+            let back: TypedArray<u32> = from_slice(&buf[pad..]).unwrap();
+            assert_eq!(v, back.as_slice());
+            borrowed += back.is_borrowed() as u32;
+        }
+        assert_eq!(1, borrowed);
+
+        let other = match Endian::NATIVE {
+            Endian::Big => Endian::Little,
+            Endian::Little => Endian::Big,
+        };
+        let data = to_vec(&TypedArray::new(&v[..]).endian(other)).unwrap();
+        let back: TypedArray<u32> = from_slice(&data).unwrap();
+        assert!(!back.is_borrowed());
+        assert_eq!(v, back.as_slice());
+    }
+}
@@ -0,0 +1,105 @@
+//! `#[serde(with = "minicbor_ser::url")]` for `url::Url`, tag 32.
+//!
+//! A URL is written as tag 32 over its text. Tag 32 and bare text are
+//! accepted when decoding, other tags are rejected, and the text is parsed
+//! as a URL.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use url::Url;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Link {
+//!     #[serde(with = "minicbor_ser::url")]
+//!     href: Url,
+//! }
+//! ```
+
+use crate::lib::*;
+use crate::tag::{Tag, MAYBE_TAGGED_NAME};
+use crate::text::URI;
+use ::url::Url;
+use serde::de::{self, Deserializer, SeqAccess, Unexpected, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+pub fn serialize<S>(url: &Url, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    Tag::<URI, _>(url.as_str()).serialize(serializer)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Url, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_newtype_struct(MAYBE_TAGGED_NAME, UrlVisitor)
+}
+
+struct UrlVisitor;
+
+impl<'de> Visitor<'de> for UrlVisitor {
+    type Value = Url;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a URL")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Url::parse(v).map_err(|_| E::invalid_value(Unexpected::Str(v), &self))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    /// `[32, text]`, a tagged URL in formats without tags.
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let tag: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        if tag != URI {
+            return Err(de::Error::invalid_value(Unexpected::Unsigned(tag), &self));
+        }
+        let text: Cow<str> = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        self.visit_str(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{from_slice, to_vec};
+    use serde::{Deserialize, Serialize};
+    use url::Url;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Link(#[serde(with = "super")] Url);
+
+    #[test]
+    fn test_url() {
+        let link = Link(Url::parse("https://example.com/a?b=c").unwrap());
+        let data = to_vec(&link).unwrap();
+        assert_eq!(&data[..3], [0xd8, 0x20, 0x78]);
+        assert_eq!(link, from_slice(&data).unwrap());
+        // bare text
+        assert_eq!(link, from_slice(&data[2..]).unwrap());
+
+        assert!(from_slice::<Link>(&to_vec(&"no url").unwrap()).is_err());
+        // other tags, 33("https://a.b")
+        let mut data = vec![0xd8, 0x21, 0x6b];
+        data.extend_from_slice(b"https://a.b");
+        let err = from_slice::<Link>(&data).unwrap_err();
+        assert!(err.to_string().contains("expected a URL"), "{}", err);
+    }
+}
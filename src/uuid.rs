@@ -0,0 +1,115 @@
+//! `#[serde(with = "minicbor_ser::uuid")]` for `uuid::Uuid`, tag 37.
+//!
+//! A UUID is written as tag 37 over its 16 bytes. Tag 37, a bare 16 byte string
+//! and the text form are accepted when decoding.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use uuid::Uuid;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct User {
+//!     #[serde(with = "minicbor_ser::uuid")]
+//!     id: Uuid,
+//! }
+//! ```
+
+use crate::lib::*;
+use crate::tag::{Tag, UUID};
+use ::uuid::Uuid;
+use serde::de::{self, Deserializer, SeqAccess, Unexpected, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+pub fn serialize<S>(uuid: &Uuid, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    Tag::<UUID, _>(Bytes(uuid.as_bytes())).serialize(serializer)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Uuid, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(UuidVisitor)
+}
+
+struct Bytes<'a>(&'a [u8; 16]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+struct UuidVisitor;
+
+impl<'de> Visitor<'de> for UuidVisitor {
+    type Value = Uuid;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a UUID as 16 bytes or text")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Uuid::from_slice(v).map_err(|_| E::invalid_length(v.len(), &self))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Uuid::try_parse(v).map_err(|_| E::invalid_value(Unexpected::Str(v), &self))
+    }
+
+    /// `[37, bytes]`, a tagged UUID in formats without tags.
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let tag: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        if tag != UUID {
+            return Err(de::Error::invalid_value(Unexpected::Unsigned(tag), &self));
+        }
+        let bytes: [u8; 16] = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok(Uuid::from_bytes(bytes))
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(test)]
+mod tests {
+    use crate::{from_slice, to_vec};
+    use serde::{Deserialize, Serialize};
+    use uuid::Uuid;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Id(#[serde(with = "super")] Uuid);
+
+    #[test]
+    fn test_uuid() {
+        let id = Id(Uuid::from_u128(0x8c8a_0d6c_0b4f_4f2a_9b1e_6a1d_2e3f_4a5b));
+        let data = to_vec(&id).unwrap();
+        assert_eq!(&data[..3], [0xd8, 0x25, 0x50]);
+        assert_eq!(&data[3..], id.0.as_bytes());
+        assert_eq!(id, from_slice(&data).unwrap());
+        // bare bytes
+        assert_eq!(id, from_slice(&data[2..]).unwrap());
+        // text
+        let text = to_vec(&"8c8a0d6c-0b4f-4f2a-9b1e-6a1d2e3f4a5b").unwrap();
+        assert_eq!(id, from_slice(&text).unwrap());
+
+        assert!(from_slice::<Id>(&data[..data.len() - 1]).is_err());
+        assert!(from_slice::<Id>(&[0xd8, 0x25, 0x41, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,1183 @@
+//! Any CBOR data item, without a Rust type for it.
+//!
+//! [`Value`] covers every major type, and keeps tags, simple values and
+//! `undefined` as they are. [`to_value`] encodes a `Serialize` type and
+//! reads it back as a `Value`, [`from_value`] deserializes a type from one.
+//!
+//! ```
+//! use minicbor_ser::value::{from_value, to_value, Value};
+//! use minicbor_ser::{from_slice, to_vec};
+//!
+//! let value = to_value(&(1u8, "a")).unwrap();
+//! assert_eq!(value, Value::Array(vec![Value::Integer(1), Value::Text("a".into())]));
+//! let pair: (u8, String) = from_value(value).unwrap();
+//! assert_eq!(pair, (1, "a".to_string()));
+//!
+//! // -2^64 and tags
+//! let value: Value = from_slice(&[0xc6, 0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]).unwrap();
+//! assert_eq!(value, Value::Tag(6, Box::new(Value::Integer(-1 << 64))));
+//! assert_eq!(to_vec(&value).unwrap()[..2], [0xc6, 0x3b]);
+//! ```
+//!
+//! As a deserializer, a `Value` visits a tag as the value it encloses, except
+//! for bignums (tags 2 and 3) that fit in 128 bits, which become integers.
+//! Types that read tags themselves, like [`Tagged`](crate::tag::Tagged),
+//! still see them.
+//!
+//! [`ValueRef`] is the borrowed counterpart of `Value`. Its text and byte
+//! strings point into the input, so reading a few fields of a message
+//! doesn't copy the rest.
+//!
+//! ```
+//! use minicbor_ser::value::ValueRef;
+//!
+//! let data = [0xa1, 0x62, b't', b'o', 0x63, b'a', b'/', b'b'];
+//! let msg: ValueRef = minicbor_ser::from_slice(&data).unwrap();
+//! let to = msg.get("to").and_then(ValueRef::as_str);
+//! assert_eq!(to, Some("a/b"));
+//! assert!(msg.to_owned().as_map().is_some());
+//! ```
+
+use crate::de::TypedSeqAccess;
+use crate::error::{de::Error as DeError, en::Error as EnError};
+use crate::lib::*;
+use crate::tag::{Tagged, TAGGED_NAME};
+use crate::{datetime, net, set, typed_array};
+use serde::de::{
+    self, Deserialize, DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess,
+    IntoDeserializer, MapAccess, SeqAccess, Unexpected, VariantAccess, Visitor,
+};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer};
+
+/// The newtype name values deserialize with.
+pub(crate) const VALUE_NAME: &str = "@@VALUE@@";
+/// The newtype name simple values serialize with.
+pub(crate) const SIMPLE_NAME: &str = "@@SIMPLE@@";
+/// The unit struct name `undefined` serializes with.
+pub(crate) const UNDEFINED_NAME: &str = "@@UNDEFINED@@";
+
+/// The variant index a tag is visited with, as `(tag, value)`.
+pub(crate) const TAG_VARIANT: u32 = 0;
+/// The variant index a simple value is visited with.
+pub(crate) const SIMPLE_VARIANT: u32 = 1;
+
+/// A CBOR data item.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Undefined,
+    Bool(bool),
+    /// An integer, CBOR integers range from -2^64 to 2^64 - 1.
+    Integer(i128),
+    /// A half, single or double precision float.
+    Float(f64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Value>),
+    /// The entries of a map, in the order they were read.
+    Map(Vec<(Value, Value)>),
+    Tag(u64, Box<Value>),
+    /// A simple value other than `false`, `true`, `null` and `undefined`.
+    Simple(u8),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i128> {
+        match self {
+            Value::Integer(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Text(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&[(Value, Value)]> {
+        match self {
+            Value::Map(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// The tag number and the value it encloses.
+    pub fn as_tag(&self) -> Option<(u64, &Value)> {
+        match self {
+            Value::Tag(tag, v) => Some((*tag, v)),
+            _ => None,
+        }
+    }
+}
+
+/// Encode `value` and read it back as a [`Value`].
+pub fn to_value<T>(value: &T) -> Result<Value, EnError>
+where
+    T: ?Sized + Serialize,
+{
+    let data = crate::to_vec(value)?;
+    crate::from_slice(&data).map_err(ser::Error::custom)
+}
+
+/// Deserialize a `T` from a [`Value`].
+pub fn from_value<T>(value: Value) -> Result<T, DeError>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(value)
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Value::Null => serializer.serialize_none(),
+            Value::Undefined => serializer.serialize_unit_struct(UNDEFINED_NAME),
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::Integer(v) => match (u64::try_from(*v), i64::try_from(*v)) {
+                (Ok(v), _) => serializer.serialize_u64(v),
+                (_, Ok(v)) => serializer.serialize_i64(v),
+                _ => serializer.serialize_i128(*v),
+            },
+            Value::Float(v) => serializer.serialize_f64(*v),
+            Value::Bytes(v) => serializer.serialize_bytes(v),
+            Value::Text(v) => serializer.serialize_str(v),
+            Value::Array(v) => {
+                let mut seq = serializer.serialize_seq(Some(v.len()))?;
+                for item in v {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            Value::Map(v) => {
+                let mut map = serializer.serialize_map(Some(v.len()))?;
+                for (key, value) in v {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            Value::Tag(tag, v) => Tagged::new(*tag, &**v).serialize(serializer),
+            Value::Simple(v) => serializer.serialize_newtype_struct(SIMPLE_NAME, v),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(VALUE_NAME, ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any CBOR data item")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E> {
+        if v < -1 << 64 {
+            let (tag, bytes) = bignum(true, (-1 - v) as u128);
+            return Ok(Value::Tag(tag, Box::new(Value::Bytes(bytes))));
+        }
+        Ok(Value::Integer(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Value::Integer(v.into()))
+    }
+
+    fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E> {
+        match u64::try_from(v) {
+            Ok(v) => Ok(Value::Integer(v.into())),
+            Err(_) => {
+                let (tag, bytes) = bignum(false, v);
+                Ok(Value::Tag(tag, Box::new(Value::Bytes(bytes))))
+            }
+        }
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(Value::Float(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Value::Text(v.into()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Value::Text(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(Value::Bytes(v.into()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Value::Bytes(v))
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Value::Undefined)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0).min(4096));
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Value::Map(entries))
+    }
+
+    /// A tag as `(tag, value)`, or a simple value.
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        match data.variant::<u32>()? {
+            (TAG_VARIANT, variant) => variant.tuple_variant(2, TagVisitor),
+            (SIMPLE_VARIANT, variant) => variant.newtype_variant().map(Value::Simple),
+            (n, _) => Err(de::Error::invalid_value(
+                Unexpected::Unsigned(n.into()),
+                &"a tag or simple value",
+            )),
+        }
+    }
+}
+
+struct TagVisitor;
+
+impl<'de> Visitor<'de> for TagVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a tag and its value")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let tag = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let value = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok(Value::Tag(tag, Box::new(value)))
+    }
+}
+
+/// Tag 2 or 3 and the big-endian bytes of `m`.
+fn bignum(negative: bool, m: u128) -> (u64, Vec<u8>) {
+    let bytes = m.to_be_bytes();
+    let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+    let tag = if negative { 3 } else { 2 };
+    (tag, bytes[start..].to_vec())
+}
+
+/// The magnitude of a bignum, if it fits in 128 bits.
+fn magnitude(value: &Value) -> Option<u128> {
+    let bytes = value.as_bytes()?;
+    let bytes = &bytes[bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len())..];
+    if bytes.len() > 16 {
+        return None;
+    }
+    Some(bytes.iter().fold(0, |m, b| m << 8 | u128::from(*b)))
+}
+
+fn visit_integer<'de, V>(v: i128, visitor: V) -> Result<V::Value, DeError>
+where
+    V: Visitor<'de>,
+{
+    match (u64::try_from(v), i64::try_from(v)) {
+        (Ok(v), _) => visitor.visit_u64(v),
+        (_, Ok(v)) => visitor.visit_i64(v),
+        _ => visitor.visit_i128(v),
+    }
+}
+
+fn visit_array<'de, V>(items: Vec<Value>, visitor: V) -> Result<V::Value, DeError>
+where
+    V: Visitor<'de>,
+{
+    let mut seq = de::value::SeqDeserializer::<_, DeError>::new(items.into_iter());
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+fn visit_map<'de, V>(entries: Vec<(Value, Value)>, visitor: V) -> Result<V::Value, DeError>
+where
+    V: Visitor<'de>,
+{
+    let mut map = de::value::MapDeserializer::<_, DeError>::new(entries.into_iter());
+    let value = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(value)
+}
+
+impl<'de> IntoDeserializer<'de, DeError> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for Value {
+    type Error = DeError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Null => visitor.visit_none(),
+            Value::Undefined => visitor.visit_unit(),
+            Value::Bool(v) => visitor.visit_bool(v),
+            Value::Integer(v) => visit_integer(v, visitor),
+            Value::Float(v) => visitor.visit_f64(v),
+            Value::Bytes(v) => visitor.visit_byte_buf(v),
+            Value::Text(v) => visitor.visit_string(v),
+            Value::Array(v) => visit_array(v, visitor),
+            Value::Map(v) => visit_map(v, visitor),
+            Value::Tag(tag @ (2 | 3), v) => match (tag, magnitude(&v)) {
+                (2, Some(m)) if m > u64::MAX.into() => visitor.visit_u128(m),
+                (2, Some(m)) => visit_integer(m as i128, visitor),
+                (_, Some(m)) if m <= i128::MAX as u128 => visit_integer(-1 - m as i128, visitor),
+                _ => v.deserialize_any(visitor),
+            },
+            Value::Tag(_, v) => v.deserialize_any(visitor),
+            Value::Simple(_) => Err(de::Error::invalid_type(
+                Unexpected::Other("simple value"),
+                &visitor,
+            )),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Null | Value::Undefined => visitor.visit_none(),
+            v => visitor.visit_some(v),
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Null | Value::Undefined => visitor.visit_unit(),
+            v => v.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match (name, self) {
+            (VALUE_NAME, Value::Tag(tag, v)) => visitor.visit_enum(SpecialAccess::Tag(tag, *v)),
+            (VALUE_NAME, Value::Simple(v)) => visitor.visit_enum(SpecialAccess::Simple(v)),
+            (TAGGED_NAME, Value::Tag(tag, v)) => {
+                visit_array(Vec::from([Value::Integer(tag.into()), *v]), visitor)
+            }
+            #[cfg(feature = "std")]
+            (
+                crate::shared::SHARED_NAME,
+                Value::Tag(tag @ (crate::shared::SHAREABLE | crate::shared::SHARED_REF), v),
+            ) => visit_array(Vec::from([Value::Integer(tag.into()), *v]), visitor),
+            (TAGGED_NAME, v) => Err(de::Error::invalid_type(v.unexpected(), &"a tag")),
+            (VALUE_NAME, v) | (datetime::DATETIME_NAME, v) | (datetime::DATE_NAME, v) => {
+                v.deserialize_any(visitor)
+            }
+            (_, v) => visitor.visit_newtype_struct(v),
+        }
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Value::Tag(set::SET, v) => v.deserialize_seq(visitor),
+            Value::Tag(tag, v) => match (typed_array::layout(tag), *v) {
+                (Some(layout), Value::Bytes(bytes)) => {
+                    if !bytes.len().is_multiple_of(layout.kind.size()) {
+                        return Err(de::Error::invalid_length(
+                            bytes.len(),
+                            &"a whole number of elements",
+                        ));
+                    }
+                    visitor.visit_seq(TypedSeqAccess {
+                        bytes: &bytes,
+                        layout,
+                    })
+                }
+                (_, v) => v.deserialize_any(visitor),
+            },
+            Value::Bytes(v) => {
+                let items = v.into_iter().map(|b| Value::Integer(b.into())).collect();
+                visit_array(items, visitor)
+            }
+            v => v.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let (variant, payload) = match self {
+            // the variants of a tag-dispatched enum are named after their tag number
+            Value::Tag(tag, v) if name == TAGGED_NAME => {
+                let index = variants
+                    .iter()
+                    .position(|v| v.parse() == Ok(tag))
+                    .ok_or_else(|| crate::error::de::unsupported_tag(tag))?;
+                (Value::Integer(index as i128), Some(*v))
+            }
+            Value::Tag(tag @ (net::IPV4 | net::IPV6), v) if name == net::IP_ADDR_NAME => {
+                (Value::Integer((tag == net::IPV6).into()), Some(*v))
+            }
+            v @ (Value::Text(_) | Value::Integer(_)) => (v, None),
+            Value::Map(mut entries) if entries.len() == 1 => {
+                let (variant, payload) = entries.remove(0);
+                (variant, Some(payload))
+            }
+            Value::Array(mut items) if !items.is_empty() => {
+                let variant = items.remove(0);
+                let payload = match items.len() {
+                    0 => None,
+                    1 => items.pop(),
+                    _ => Some(Value::Array(items)),
+                };
+                (variant, payload)
+            }
+            v => {
+                return Err(de::Error::invalid_type(
+                    v.unexpected(),
+                    &"a variant as text, integer, map or array",
+                ))
+            }
+        };
+        visitor.visit_enum(EnumDeserializer { variant, payload })
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf tuple tuple_struct map struct identifier
+    }
+}
+
+impl Value {
+    fn unexpected(&self) -> Unexpected<'_> {
+        match self {
+            Value::Null | Value::Undefined => Unexpected::Unit,
+            Value::Bool(v) => Unexpected::Bool(*v),
+            Value::Integer(_) => Unexpected::Other("integer"),
+            Value::Float(v) => Unexpected::Float(*v),
+            Value::Bytes(v) => Unexpected::Bytes(v),
+            Value::Text(v) => Unexpected::Str(v),
+            Value::Array(_) => Unexpected::Seq,
+            Value::Map(_) => Unexpected::Map,
+            Value::Tag(..) => Unexpected::Other("tag"),
+            Value::Simple(_) => Unexpected::Other("simple value"),
+        }
+    }
+}
+
+/// A tag or simple value of a `Value`, see `Deserializer::deserialize_newtype_struct`.
+enum SpecialAccess {
+    Tag(u64, Value),
+    Simple(u8),
+}
+
+impl<'de> EnumAccess<'de> for SpecialAccess {
+    type Error = DeError;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = match self {
+            SpecialAccess::Tag(..) => TAG_VARIANT,
+            SpecialAccess::Simple(_) => SIMPLE_VARIANT,
+        };
+        let variant: de::value::U32Deserializer<DeError> = variant.into_deserializer();
+        Ok((seed.deserialize(variant)?, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for SpecialAccess {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Err(de::Error::invalid_type(
+            Unexpected::Other("tag"),
+            &"unit variant",
+        ))
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self {
+            SpecialAccess::Simple(v) => seed.deserialize(v.into_deserializer()),
+            SpecialAccess::Tag(..) => Err(de::Error::invalid_type(
+                Unexpected::TupleVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            SpecialAccess::Tag(tag, v) => {
+                visit_array(Vec::from([Value::Integer(tag.into()), v]), visitor)
+            }
+            SpecialAccess::Simple(_) => Err(de::Error::invalid_type(
+                Unexpected::NewtypeVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(de::Error::invalid_type(
+            Unexpected::TupleVariant,
+            &"struct variant",
+        ))
+    }
+}
+
+/// A variant and its payload, from a map entry, an array or a tag.
+struct EnumDeserializer {
+    variant: Value,
+    payload: Option<Value>,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = DeError;
+    type Variant = Payload;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        Ok((seed.deserialize(self.variant)?, Payload(self.payload)))
+    }
+}
+
+/// The payload of a variant, none for unit variants.
+struct Payload(Option<Value>);
+
+impl<'de> VariantAccess<'de> for Payload {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.0 {
+            None => Ok(()),
+            Some(v) => Err(de::Error::invalid_type(v.unexpected(), &"unit variant")),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.0 {
+            Some(v) => seed.deserialize(v),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"newtype variant",
+            )),
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Some(v) => v.deserialize_seq(visitor),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"tuple variant",
+            )),
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Some(v) => v.deserialize_any(visitor),
+            None => Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"struct variant",
+            )),
+        }
+    }
+}
+
+/// A CBOR data item whose text and byte strings borrow from the input.
+///
+/// Strings that can't be borrowed, like indefinite length ones, are owned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueRef<'a> {
+    Null,
+    Undefined,
+    Bool(bool),
+    /// An integer, CBOR integers range from -2^64 to 2^64 - 1.
+    Integer(i128),
+    /// A half, single or double precision float.
+    Float(f64),
+    Bytes(Cow<'a, [u8]>),
+    Text(Cow<'a, str>),
+    Array(Vec<ValueRef<'a>>),
+    /// The entries of a map, in the order they were read.
+    Map(Vec<(ValueRef<'a>, ValueRef<'a>)>),
+    Tag(u64, Box<ValueRef<'a>>),
+    /// A simple value other than `false`, `true`, `null` and `undefined`.
+    Simple(u8),
+}
+
+impl<'a> ValueRef<'a> {
+    /// Copy the strings into an owned [`Value`].
+    #[allow(clippy::should_implement_trait)]
+    pub fn to_owned(&self) -> Value {
+        match self {
+            ValueRef::Null => Value::Null,
+            ValueRef::Undefined => Value::Undefined,
+            ValueRef::Bool(v) => Value::Bool(*v),
+            ValueRef::Integer(v) => Value::Integer(*v),
+            ValueRef::Float(v) => Value::Float(*v),
+            ValueRef::Bytes(v) => Value::Bytes(v.to_vec()),
+            ValueRef::Text(v) => Value::Text(v.to_string()),
+            ValueRef::Array(v) => Value::Array(v.iter().map(ValueRef::to_owned).collect()),
+            ValueRef::Map(v) => Value::Map(
+                v.iter()
+                    .map(|(key, value)| (key.to_owned(), value.to_owned()))
+                    .collect(),
+            ),
+            ValueRef::Tag(tag, v) => Value::Tag(*tag, Box::new(ValueRef::to_owned(v))),
+            ValueRef::Simple(v) => Value::Simple(*v),
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, ValueRef::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            ValueRef::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i128> {
+        match self {
+            ValueRef::Integer(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            ValueRef::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            ValueRef::Bytes(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            ValueRef::Text(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[ValueRef<'a>]> {
+        match self {
+            ValueRef::Array(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&[(ValueRef<'a>, ValueRef<'a>)]> {
+        match self {
+            ValueRef::Map(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// The tag number and the value it encloses.
+    pub fn as_tag(&self) -> Option<(u64, &ValueRef<'a>)> {
+        match self {
+            ValueRef::Tag(tag, v) => Some((*tag, v)),
+            _ => None,
+        }
+    }
+
+    /// The value of the first entry of a map with the text key `key`.
+    pub fn get(&self, key: &str) -> Option<&ValueRef<'a>> {
+        self.as_map()?
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v)
+    }
+}
+
+impl Serialize for ValueRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            ValueRef::Null => serializer.serialize_none(),
+            ValueRef::Undefined => serializer.serialize_unit_struct(UNDEFINED_NAME),
+            ValueRef::Bool(v) => serializer.serialize_bool(*v),
+            ValueRef::Integer(v) => match (u64::try_from(*v), i64::try_from(*v)) {
+                (Ok(v), _) => serializer.serialize_u64(v),
+                (_, Ok(v)) => serializer.serialize_i64(v),
+                _ => serializer.serialize_i128(*v),
+            },
+            ValueRef::Float(v) => serializer.serialize_f64(*v),
+            ValueRef::Bytes(v) => serializer.serialize_bytes(v),
+            ValueRef::Text(v) => serializer.serialize_str(v),
+            ValueRef::Array(v) => {
+                let mut seq = serializer.serialize_seq(Some(v.len()))?;
+                for item in v {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            ValueRef::Map(v) => {
+                let mut map = serializer.serialize_map(Some(v.len()))?;
+                for (key, value) in v {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            ValueRef::Tag(tag, v) => Tagged::new(*tag, &**v).serialize(serializer),
+            ValueRef::Simple(v) => serializer.serialize_newtype_struct(SIMPLE_NAME, v),
+        }
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for ValueRef<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(VALUE_NAME, ValueRefVisitor(PhantomData))
+    }
+}
+
+struct ValueRefVisitor<'a>(PhantomData<ValueRef<'a>>);
+
+impl<'de: 'a, 'a> Visitor<'de> for ValueRefVisitor<'a> {
+    type Value = ValueRef<'a>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any CBOR data item")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(ValueRef::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(ValueRef::Integer(v.into()))
+    }
+
+    fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E> {
+        if v < -1 << 64 {
+            let (tag, bytes) = bignum(true, (-1 - v) as u128);
+            return Ok(ValueRef::Tag(tag, Box::new(ValueRef::Bytes(bytes.into()))));
+        }
+        Ok(ValueRef::Integer(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(ValueRef::Integer(v.into()))
+    }
+
+    fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E> {
+        match u64::try_from(v) {
+            Ok(v) => Ok(ValueRef::Integer(v.into())),
+            Err(_) => {
+                let (tag, bytes) = bignum(false, v);
+                Ok(ValueRef::Tag(tag, Box::new(ValueRef::Bytes(bytes.into()))))
+            }
+        }
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(ValueRef::Float(v))
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(ValueRef::Text(Cow::Borrowed(v)))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(ValueRef::Text(Cow::Owned(v.into())))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(ValueRef::Text(Cow::Owned(v)))
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        Ok(ValueRef::Bytes(Cow::Borrowed(v)))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(ValueRef::Bytes(Cow::Owned(v.into())))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(ValueRef::Bytes(Cow::Owned(v)))
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E> {
+        Ok(ValueRef::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        ValueRef::deserialize(deserializer)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(ValueRef::Undefined)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(ValueRef::Array(items))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0).min(4096));
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(ValueRef::Map(entries))
+    }
+
+    /// A tag as `(tag, value)`, or a simple value.
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        match data.variant::<u32>()? {
+            (TAG_VARIANT, variant) => variant.tuple_variant(2, TagRefVisitor(PhantomData)),
+            (SIMPLE_VARIANT, variant) => variant.newtype_variant().map(ValueRef::Simple),
+            (n, _) => Err(de::Error::invalid_value(
+                Unexpected::Unsigned(n.into()),
+                &"a tag or simple value",
+            )),
+        }
+    }
+}
+
+struct TagRefVisitor<'a>(PhantomData<ValueRef<'a>>);
+
+impl<'de: 'a, 'a> Visitor<'de> for TagRefVisitor<'a> {
+    type Value = ValueRef<'a>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a tag and its value")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let tag = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let value = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok(ValueRef::Tag(tag, Box::new(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(not(feature = "std"))]
+    use alloc::vec;
+    use crate::{from_slice, to_vec, Config};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Dot,
+        Circle(u32),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Doc {
+        name: String,
+        shapes: Vec<Shape>,
+        at: Tagged<u8>,
+        data: Option<Vec<u8>>,
+        big: u128,
+    }
+
+    #[test]
+    fn test_major_types() {
+        // [undefined, simple(99), -2^64, 1.5, h'01', {"a": null}, 100(true)]
+        let data = [
+            0x87, 0xf7, 0xf8, 0x63, 0x3b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xf9,
+            0x3e, 0x00, 0x41, 0x01, 0xa1, 0x61, b'a', 0xf6, 0xd8, 0x64, 0xf5,
+        ];
+        let value: Value = from_slice(&data).unwrap();
+        let expected = Value::Array(vec![
+            Value::Undefined,
+            Value::Simple(99),
+            Value::Integer(-1 << 64),
+            Value::Float(1.5),
+            Value::Bytes(vec![1]),
+            Value::Map(vec![(Value::Text("a".into()), Value::Null)]),
+            Value::Tag(100, Box::new(Value::Bool(true))),
+        ]);
+        assert_eq!(value, expected);
+        let cfg = Config::default().preferred_float(true);
+        assert_eq!(crate::to_vec_cfg(&value, cfg).unwrap(), data);
+
+        // bignums stay tags, indefinite lengths are not kept
+        let value: Value = from_slice(&[0xc2, 0x41, 0x01]).unwrap();
+        assert_eq!(value.as_tag().map(|t| t.0), Some(2));
+        let value: Value = from_slice(&[0x9f, 0x01, 0xff]).unwrap();
+        assert_eq!(to_vec(&value).unwrap(), [0x81, 0x01]);
+
+        assert!(to_vec(&Value::Simple(24)).is_err());
+    }
+
+    #[test]
+    fn test_to_from_value() {
+        let doc = Doc {
+            name: "a".to_string(),
+            shapes: vec![Shape::Dot, Shape::Circle(2), Shape::Rect { w: 1, h: 2 }],
+            at: Tagged::new(7, 1),
+            data: Some(vec![1, 2]),
+            big: u128::MAX,
+        };
+        let value = to_value(&doc).unwrap();
+        let map = value.as_map().unwrap();
+        assert_eq!(
+            map[0],
+            (Value::Text("name".into()), Value::Text("a".into()))
+        );
+        assert_eq!(map[2].1, Value::Tag(7, Box::new(Value::Integer(1))));
+        assert_eq!(doc, from_value(value.clone()).unwrap());
+        assert_eq!(to_vec(&doc).unwrap(), to_vec(&value).unwrap());
+
+        // values inside other types
+        let v: (Value, u8) = from_slice(&[0x82, 0xf7, 0x01]).unwrap();
+        assert_eq!(v, (Value::Undefined, 1));
+        let v: (u8, Option<u8>) =
+            from_value(Value::Array(vec![Value::Integer(1), Value::Undefined])).unwrap();
+        assert_eq!(v, (1, None));
+        let v: Value = from_value(Value::Tag(99, Box::new(Value::Simple(99)))).unwrap();
+        assert_eq!(v, Value::Tag(99, Box::new(Value::Simple(99))));
+
+        assert!(from_value::<String>(Value::Integer(1)).is_err());
+        assert!(
+            from_value::<(u8,)>(Value::Array(vec![Value::Integer(1), Value::Integer(2)])).is_err()
+        );
+    }
+
+    #[test]
+    fn test_value_ref() {
+        // {"to": "a/b", "body": [h'0102', 6("x")], "n": -2^64}
+        let data = [
+            0xa3, 0x62, b't', b'o', 0x63, b'a', b'/', b'b', 0x64, b'b', b'o', b'd', b'y', 0x82,
+            0x42, 0x01, 0x02, 0xc6, 0x61, b'x', 0x61, b'n', 0x3b, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xff, 0xff,
+        ];
+        let msg: ValueRef = from_slice(&data).unwrap();
+        let input = data.as_ptr_range();
+        let to = msg.get("to").and_then(ValueRef::as_str).unwrap();
+        assert_eq!(to, "a/b");
+        assert!(input.contains(&to.as_ptr()));
+        let body = msg.get("body").and_then(ValueRef::as_array).unwrap();
+        assert!(matches!(&body[0], ValueRef::Bytes(Cow::Borrowed(b)) if b == &[1, 2]));
+        assert_eq!(
+            body[1].as_tag().map(|(tag, v)| (tag, v.as_str())),
+            Some((6, Some("x")))
+        );
+        assert_eq!(msg.get("n").and_then(ValueRef::as_integer), Some(-1 << 64));
+        assert_eq!(msg.get("none"), None);
+
+        let owned = msg.to_owned();
+        assert_eq!(owned, from_slice::<Value>(&data).unwrap());
+        assert_eq!(to_vec(&msg).unwrap(), data);
+        assert_eq!(to_vec(&owned).unwrap(), data);
+
+        // indefinite length text can't be borrowed
+        let v: ValueRef = from_slice(&[0x7f, 0x61, b'a', 0x61, b'b', 0xff]).unwrap();
+        assert_eq!(v, ValueRef::Text(Cow::Owned("ab".to_string())));
+    }
+}